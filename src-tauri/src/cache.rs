@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use reqwest::header::{HeaderMap, HeaderValue, ETAG, LAST_MODIFIED};
+
+/// Maximum number of responses kept, the least recently stored ones are evicted first
+const MAX_ENTRIES: usize = 500;
+
+/// A successful response that can be revalidated with a conditional request
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub etag: Option<HeaderValue>,
+    pub last_modified: Option<HeaderValue>,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    /// Build a cache entry from a response, `None` if it carries no validators
+    pub fn new(headers: &HeaderMap, body: &[u8]) -> Option<Self> {
+        let etag = headers.get(ETAG).cloned();
+        let last_modified = headers.get(LAST_MODIFIED).cloned();
+        if etag.is_none() && last_modified.is_none() {
+            return None;
+        }
+        Some(Self {
+            etag,
+            last_modified,
+            headers: headers.clone(),
+            body: body.to_vec(),
+        })
+    }
+}
+
+#[derive(Default)]
+struct Entries {
+    responses: HashMap<String, (u64, CachedResponse)>,
+    generation: u64,
+}
+
+/// Cache of response bodies keyed by token and request URL, used for `If-None-Match` requests
+///
+/// GitHub does not count `304 Not Modified` responses against the rate limit, so
+/// revalidating unchanged resources is effectively free.
+#[derive(Default)]
+pub struct ResponseCache {
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let entries = self.entries.lock().unwrap();
+        entries.responses.get(url).map(|(_, r)| r.clone())
+    }
+
+    pub fn insert(&self, url: impl Into<String>, response: CachedResponse) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        let generation = entries.generation;
+        entries.responses.insert(url.into(), (generation, response));
+
+        if entries.responses.len() > MAX_ENTRIES {
+            let oldest = entries
+                .responses
+                .iter()
+                .min_by_key(|(_, (generation, _))| *generation)
+                .map(|(url, _)| url.clone());
+            if let Some(oldest) = oldest {
+                entries.responses.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, HeaderValue, ETAG};
+
+    use super::{CachedResponse, ResponseCache, MAX_ENTRIES};
+
+    #[test]
+    fn only_caches_responses_with_validators() {
+        assert!(CachedResponse::new(&HeaderMap::new(), b"{}").is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static(r#""abc""#));
+        let response = CachedResponse::new(&headers, b"{}").unwrap();
+        assert_eq!(response.etag.unwrap(), r#""abc""#);
+    }
+
+    #[test]
+    fn evicts_oldest_entry() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static(r#""abc""#));
+        let response = CachedResponse::new(&headers, b"{}").unwrap();
+
+        let cache = ResponseCache::default();
+        for i in 0..=MAX_ENTRIES {
+            cache.insert(format!("/url/{i}"), response.clone());
+        }
+        assert!(cache.get("/url/0").is_none());
+        assert!(cache.get("/url/1").is_some());
+        assert!(cache.get(&format!("/url/{MAX_ENTRIES}")).is_some());
+    }
+}
@@ -0,0 +1,912 @@
+use std::{ffi::OsString, io::Write, str::FromStr, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand, ValueEnum};
+use color_eyre::eyre::{self, Context};
+use serde::Serialize;
+
+use crate::{
+    config::{self, AppConfig, ProgressStrategy, DEFAULT_HOST},
+    error::Error,
+    estimate::{self, WorkflowDurations},
+    fetcher::{Fetcher, Pr, PrState, Status, WorkflowStatus},
+    gh_cli::GhCli,
+    github::{RunConclusion, RunJob, RunStatus},
+    token,
+    token_store::TokenStore,
+};
+
+/// Headless interface to the same machinery the desktop app uses
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Run without the desktop window
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Parse the command given on the command line, `None` if the desktop app should start
+    ///
+    /// The OS or a launcher can pass arguments of its own to the app, e.g. `-psn_*` on macOS
+    /// or a file to open on Linux, so only arguments naming a subcommand are an error.
+    pub fn parse_command<I, T>(args: I) -> Result<Option<Command>, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let err = match Self::try_parse_from(&args) {
+            Ok(cli) => return Ok(cli.command),
+            Err(err) => err,
+        };
+        let asked_for_cli = matches!(
+            err.kind(),
+            ErrorKind::DisplayHelp
+                | ErrorKind::DisplayVersion
+                | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+        ) || args
+            .get(1)
+            .is_some_and(|arg| Self::command().find_subcommand(arg).is_some());
+        if asked_for_cli {
+            return Err(err);
+        }
+        tracing::debug!(?args, "ignoring arguments not meant for the CLI");
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Watch a PR until its workflow run finishes, exiting with a code reflecting the outcome
+    ///
+    /// Exit codes: 0 succeeded, 1 failed, 2 cancelled, 3 any other outcome, 4 error.
+    Watch {
+        /// The PR to watch, e.g. `owner/repo#123`
+        pr: PrReference,
+
+        /// Workflow to watch, by file name (`ci.yml`), name or id. Watches every workflow
+        /// that ran on the PR if not given
+        #[arg(long)]
+        workflow: Option<String>,
+
+        /// GitHub token, defaults to the `GH_TOKEN` or `GITHUB_TOKEN` environment variables,
+        /// then the token the desktop app is logged in with
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Name of the configured host the PR lives on
+        #[arg(long, default_value = DEFAULT_HOST)]
+        host: String,
+
+        /// Seconds between polls, defaults to the `poll_interval_secs` config value
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+    },
+
+    /// Print the current status of one or more PRs as JSON
+    ///
+    /// Exits with 0 if the status of every PR was fetched, and 4 otherwise.
+    Status {
+        /// The PRs to report on, e.g. `owner/repo#123`
+        #[arg(required = true)]
+        prs: Vec<PrReference>,
+
+        /// Workflow to report on, by file name (`ci.yml`), name or id. Reports every
+        /// workflow that ran on the PR if not given
+        #[arg(long)]
+        workflow: Option<String>,
+
+        /// GitHub token, defaults to the `GH_TOKEN` or `GITHUB_TOKEN` environment variables,
+        /// then the token the desktop app is logged in with
+        #[arg(long)]
+        token: Option<String>,
+
+        /// Name of the configured host the PRs live on
+        #[arg(long, default_value = DEFAULT_HOST)]
+        host: String,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+        format: OutputFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// A single JSON document containing every PR
+    Json,
+    /// One JSON document per line, one line per PR
+    Ndjson,
+}
+
+/// Version of the `status` output schema
+///
+/// Bump this whenever a field is removed or changes meaning, adding fields is
+/// backwards compatible.
+const STATUS_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+struct StatusDocument<'a> {
+    schema_version: u32,
+    results: &'a [StatusResult],
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResult {
+    owner: String,
+    repo: String,
+    number: u64,
+    /// `None` if fetching the status failed
+    pr: Option<PrOutput>,
+    error: Option<String>,
+}
+
+/// A PR in the `status` output
+///
+/// The output types are separate from the ones the app uses internally so that changing
+/// those does not change the schema behind [`STATUS_SCHEMA_VERSION`]'s back.
+#[derive(Debug, Serialize)]
+struct PrOutput {
+    status: StatusOutput,
+    state: PrStateOutput,
+    /// Unix timestamp (in seconds) the PR was merged or closed at
+    closed_at: Option<i64>,
+    title: String,
+    branch: String,
+    description: String,
+    num_steps: u64,
+    num_complete_steps: u64,
+    pr_url: String,
+    run_url: String,
+    workflows: Vec<WorkflowOutput>,
+    estimated_progress: Option<f32>,
+    /// Unix timestamp (in seconds)
+    estimated_completion: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct WorkflowOutput {
+    workflow_id: u64,
+    run_id: u64,
+    name: String,
+    status: StatusOutput,
+    num_steps: u64,
+    num_complete_steps: u64,
+    run_url: String,
+    jobs: Vec<JobOutput>,
+}
+
+#[derive(Debug, Serialize)]
+struct JobOutput {
+    id: u64,
+    name: String,
+    status: RunStatusOutput,
+    conclusion: Option<RunConclusionOutput>,
+    started_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    steps: Vec<StepOutput>,
+}
+
+#[derive(Debug, Serialize)]
+struct StepOutput {
+    name: String,
+    status: RunStatusOutput,
+    conclusion: Option<RunConclusionOutput>,
+    started_at: Option<DateTime<Utc>>,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+enum StatusOutput {
+    Queued,
+    Waiting,
+    InProgress(f32),
+    Succeeded,
+    Failed,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    Neutral,
+    Stale,
+    StartupFailure,
+    Unknown,
+}
+
+impl From<Status> for StatusOutput {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Queued => Self::Queued,
+            Status::Waiting => Self::Waiting,
+            Status::InProgress(progress) => Self::InProgress(progress),
+            Status::Succeeded => Self::Succeeded,
+            Status::Failed => Self::Failed,
+            Status::Cancelled => Self::Cancelled,
+            Status::Skipped => Self::Skipped,
+            Status::TimedOut => Self::TimedOut,
+            Status::ActionRequired => Self::ActionRequired,
+            Status::Neutral => Self::Neutral,
+            Status::Stale => Self::Stale,
+            Status::StartupFailure => Self::StartupFailure,
+            Status::Unknown => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+enum PrStateOutput {
+    #[serde(rename = "open")]
+    Open,
+    #[serde(rename = "merged")]
+    Merged,
+    #[serde(rename = "closed")]
+    Closed,
+}
+
+impl From<PrState> for PrStateOutput {
+    fn from(state: PrState) -> Self {
+        match state {
+            PrState::Open => Self::Open,
+            PrState::Merged => Self::Merged,
+            PrState::Closed => Self::Closed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+enum RunStatusOutput {
+    #[serde(rename = "requested")]
+    Requested,
+    #[serde(rename = "waiting")]
+    Waiting,
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "queued")]
+    Queued,
+    #[serde(rename = "in_progress")]
+    InProgress,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+impl From<RunStatus> for RunStatusOutput {
+    fn from(status: RunStatus) -> Self {
+        match status {
+            RunStatus::Requested => Self::Requested,
+            RunStatus::Waiting => Self::Waiting,
+            RunStatus::Pending => Self::Pending,
+            RunStatus::Queued => Self::Queued,
+            RunStatus::InProgress => Self::InProgress,
+            RunStatus::Completed => Self::Completed,
+            RunStatus::Unknown => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+enum RunConclusionOutput {
+    #[serde(rename = "success")]
+    Success,
+    #[serde(rename = "failure")]
+    Failure,
+    #[serde(rename = "cancelled")]
+    Cancelled,
+    #[serde(rename = "skipped")]
+    Skipped,
+    #[serde(rename = "timed_out")]
+    TimedOut,
+    #[serde(rename = "action_required")]
+    ActionRequired,
+    #[serde(rename = "neutral")]
+    Neutral,
+    #[serde(rename = "stale")]
+    Stale,
+    #[serde(rename = "startup_failure")]
+    StartupFailure,
+    #[serde(rename = "unknown")]
+    Unknown,
+}
+
+impl From<RunConclusion> for RunConclusionOutput {
+    fn from(conclusion: RunConclusion) -> Self {
+        match conclusion {
+            RunConclusion::Success => Self::Success,
+            RunConclusion::Failure => Self::Failure,
+            RunConclusion::Cancelled => Self::Cancelled,
+            RunConclusion::Skipped => Self::Skipped,
+            RunConclusion::TimedOut => Self::TimedOut,
+            RunConclusion::ActionRequired => Self::ActionRequired,
+            RunConclusion::Neutral => Self::Neutral,
+            RunConclusion::Stale => Self::Stale,
+            RunConclusion::StartupFailure => Self::StartupFailure,
+            RunConclusion::Unknown => Self::Unknown,
+        }
+    }
+}
+
+impl From<Pr> for PrOutput {
+    fn from(pr: Pr) -> Self {
+        Self {
+            status: pr.status.into(),
+            state: pr.state.into(),
+            closed_at: pr.closed_at,
+            title: pr.title,
+            branch: pr.branch,
+            description: pr.description,
+            num_steps: pr.num_steps,
+            num_complete_steps: pr.num_complete_steps,
+            pr_url: pr.pr_url,
+            run_url: pr.run_url,
+            workflows: pr.workflows.into_iter().map(Into::into).collect(),
+            estimated_progress: pr.estimated_progress,
+            estimated_completion: pr.estimated_completion,
+        }
+    }
+}
+
+impl From<WorkflowStatus> for WorkflowOutput {
+    fn from(workflow: WorkflowStatus) -> Self {
+        Self {
+            workflow_id: workflow.workflow_id,
+            run_id: workflow.run_id,
+            name: workflow.name,
+            status: workflow.status.into(),
+            num_steps: workflow.num_steps,
+            num_complete_steps: workflow.num_complete_steps,
+            run_url: workflow.run_url,
+            jobs: workflow.jobs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<RunJob> for JobOutput {
+    fn from(job: RunJob) -> Self {
+        Self {
+            id: job.id,
+            name: job.name,
+            status: job.status.into(),
+            conclusion: job.conclusion.map(Into::into),
+            started_at: job.started_at,
+            completed_at: job.completed_at,
+            steps: job
+                .steps
+                .into_iter()
+                .map(|step| StepOutput {
+                    name: step.name,
+                    status: step.status.into(),
+                    conclusion: step.conclusion.map(Into::into),
+                    started_at: step.started_at,
+                    completed_at: step.completed_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct StatusLine<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    result: &'a StatusResult,
+}
+
+/// Reference to a PR in the form `owner/repo#123`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrReference {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl FromStr for PrReference {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (slug, number) = s
+            .split_once('#')
+            .ok_or_else(|| eyre::eyre!("expected `owner/repo#number`, got '{s}'"))?;
+        let (owner, repo) = slug
+            .split_once('/')
+            .ok_or_else(|| eyre::eyre!("expected `owner/repo#number`, got '{s}'"))?;
+        if owner.is_empty() || repo.is_empty() {
+            eyre::bail!("expected `owner/repo#number`, got '{s}'");
+        }
+        let number = number
+            .parse()
+            .wrap_err_with(|| format!("invalid PR number '{number}'"))?;
+        Ok(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number,
+        })
+    }
+}
+
+const EXIT_ERROR: i32 = 4;
+
+/// Run a CLI command, returning the process exit code
+pub fn run(command: Command) -> i32 {
+    let config = Arc::new(AppConfig::from_default_path().unwrap_or_default());
+    tracing::debug!(?config, "loaded config");
+
+    let result = tauri::async_runtime::block_on(async move {
+        match command {
+            Command::Watch {
+                pr,
+                workflow,
+                token,
+                host,
+                interval,
+            } => {
+                let token = resolve_token(token, &host, Some(&pr.owner)).await?;
+                let interval = Duration::from_secs(interval.unwrap_or(config.poll_interval_secs));
+                let mut progress = Progress::new(config.progress);
+                let fetcher = fetcher_for_host(config, &host)?;
+                watch(
+                    &fetcher,
+                    &token,
+                    &pr,
+                    workflow.as_deref(),
+                    interval,
+                    &mut progress,
+                )
+                .await
+            }
+            Command::Status {
+                prs,
+                workflow,
+                token,
+                host,
+                format,
+            } => {
+                // one token is used for every PR, so owner routing only applies to a single owner
+                let owner = prs
+                    .first()
+                    .map(|pr| pr.owner.as_str())
+                    .filter(|owner| prs.iter().all(|pr| pr.owner == *owner));
+                let token = resolve_token(token, &host, owner).await?;
+                let mut progress = Progress::new(config.progress);
+                let fetcher = fetcher_for_host(config, &host)?;
+                status(
+                    &fetcher,
+                    &token,
+                    &prs,
+                    workflow.as_deref(),
+                    format,
+                    &mut progress,
+                )
+                .await
+            }
+        }
+    });
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            EXIT_ERROR
+        }
+    }
+}
+
+async fn resolve_token(
+    token: Option<String>,
+    host: &str,
+    owner: Option<&str>,
+) -> eyre::Result<String> {
+    if let Some(token) = token
+        .or_else(|| std::env::var("GH_TOKEN").ok())
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+    {
+        return Ok(token);
+    }
+
+    // fall back to the token the desktop app logged in with, then the gh CLI's
+    let tokens = TokenStore::open(&config::config_dir()?).wrap_err("opening token store")?;
+    if let Some(resolved) = tokens.resolve(host, owner).await {
+        return Ok(resolved.token);
+    }
+    GhCli::default().token(host).await.ok_or_else(|| {
+        eyre::eyre!("no token for {host}, pass --token, set GH_TOKEN or log in with the app")
+    })
+}
+
+fn fetcher_for_host(config: Arc<AppConfig>, host: &str) -> eyre::Result<Fetcher> {
+    let host_config = config
+        .hosts()
+        .into_iter()
+        .find(|h| h.name == host)
+        .ok_or_else(|| eyre::eyre!("host '{host}' is not configured"))?;
+    Fetcher::for_host(&host_config, config)
+}
+
+async fn watch(
+    fetcher: &Fetcher,
+    token: &str,
+    pr: &PrReference,
+    workflow: Option<&str>,
+    interval: Duration,
+    progress: &mut Progress,
+) -> eyre::Result<i32> {
+    let workflow_id = match workflow {
+        Some(workflow) => Some(resolve_workflow(fetcher, token, pr, workflow).await?),
+        None => None,
+    };
+
+    let mut stdout = std::io::stdout();
+    let mut warned_expiry = false;
+    loop {
+        let result = fetch(fetcher, token, pr, workflow_id, progress).await;
+
+        // clear the line and redraw it in place
+        write!(stdout, "\r\x1b[2K")?;
+        if !warned_expiry {
+            stdout.flush()?;
+            warned_expiry = warn_if_expiring(fetcher, token);
+        }
+        match result {
+            Ok(status) => {
+                write!(stdout, "{}", render(pr, &status))?;
+                if let Some(code) = exit_code(status.status) {
+                    writeln!(stdout)?;
+                    return Ok(code);
+                }
+            }
+            Err(e) => {
+                tracing::debug!(error = ?e, "error fetching pr status");
+                write!(stdout, "{}/{}#{}: {e}", pr.owner, pr.repo, pr.number)?;
+                // only keep polling through errors that may go away on their own
+                let transient = e.downcast_ref::<Error>().is_some_and(Error::is_transient);
+                if !transient {
+                    writeln!(stdout)?;
+                    return Ok(EXIT_ERROR);
+                }
+            }
+        }
+        stdout.flush()?;
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Warn on stderr if GitHub reported that the token expires soon, returning whether it did
+fn warn_if_expiring(fetcher: &Fetcher, token: &str) -> bool {
+    let Some(expires_at) = fetcher
+        .token_expiry(token)
+        .filter(|at| token::expires_soon(*at, Utc::now().timestamp()))
+    else {
+        return false;
+    };
+    let expires = DateTime::from_timestamp(expires_at, 0)
+        .map(|at| at.with_timezone(&Local).format("%d %b %H:%M").to_string())
+        .unwrap_or_default();
+    eprintln!("Warning: the GitHub token expires {expires}, create a new one to keep watching PRs");
+    true
+}
+
+async fn status(
+    fetcher: &Fetcher,
+    token: &str,
+    prs: &[PrReference],
+    workflow: Option<&str>,
+    format: OutputFormat,
+    progress: &mut Progress,
+) -> eyre::Result<i32> {
+    let mut results = Vec::with_capacity(prs.len());
+    for pr in prs {
+        let result = match workflow {
+            Some(workflow) => match resolve_workflow(fetcher, token, pr, workflow).await {
+                Ok(workflow_id) => fetch(fetcher, token, pr, Some(workflow_id), progress).await,
+                Err(e) => Err(e),
+            },
+            None => fetch(fetcher, token, pr, None, progress).await,
+        };
+        let (pr_status, error) = match result {
+            Ok(pr_status) => (Some(pr_status.into()), None),
+            Err(e) => {
+                tracing::debug!(error = ?e, ?pr, "error fetching pr status");
+                (None, Some(format!("{e:#}")))
+            }
+        };
+        results.push(StatusResult {
+            owner: pr.owner.clone(),
+            repo: pr.repo.clone(),
+            number: pr.number,
+            pr: pr_status,
+            error,
+        });
+    }
+
+    warn_if_expiring(fetcher, token);
+
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        OutputFormat::Json => {
+            let document = StatusDocument {
+                schema_version: STATUS_SCHEMA_VERSION,
+                results: &results,
+            };
+            serde_json::to_writer_pretty(&mut stdout, &document)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Ndjson => {
+            for result in &results {
+                let line = StatusLine {
+                    schema_version: STATUS_SCHEMA_VERSION,
+                    result,
+                };
+                serde_json::to_writer(&mut stdout, &line)?;
+                writeln!(stdout)?;
+            }
+        }
+    }
+
+    if results.iter().any(|r| r.error.is_some()) {
+        Ok(EXIT_ERROR)
+    } else {
+        Ok(0)
+    }
+}
+
+async fn fetch(
+    fetcher: &Fetcher,
+    token: &str,
+    pr: &PrReference,
+    workflow_id: Option<u64>,
+    progress: &mut Progress,
+) -> eyre::Result<Pr> {
+    let mut status = match workflow_id {
+        Some(workflow_id) => {
+            fetcher
+                .fetch(token, &pr.owner, &pr.repo, workflow_id, pr.number)
+                .await?
+        }
+        None => {
+            fetcher
+                .fetch_pr_status(token, &pr.owner, &pr.repo, pr.number)
+                .await?
+        }
+    };
+    progress.apply(fetcher, token, pr, &mut status).await;
+    Ok(status)
+}
+
+/// Measures progress the way the config file asks for
+///
+/// There is no local history to weight steps by duration with, so the durations of each
+/// workflow's recent successful runs are fetched from GitHub instead, once per workflow.
+struct Progress {
+    strategy: ProgressStrategy,
+    durations: WorkflowDurations,
+}
+
+impl Progress {
+    fn new(strategy: ProgressStrategy) -> Self {
+        Self {
+            strategy,
+            durations: WorkflowDurations::new(),
+        }
+    }
+
+    async fn apply(&mut self, fetcher: &Fetcher, token: &str, pr: &PrReference, status: &mut Pr) {
+        if self.strategy != ProgressStrategy::Duration {
+            return;
+        }
+        for workflow in &status.workflows {
+            let id = workflow.workflow_id;
+            if !workflow.status.is_running() || self.durations.contains_key(&id) {
+                continue;
+            }
+            match fetcher.past_durations(&pr.owner, &pr.repo, id, token).await {
+                Ok(durations) => {
+                    self.durations.insert(id, durations);
+                }
+                Err(e) => tracing::debug!(error = ?e, %id, "error fetching past run durations"),
+            }
+        }
+        estimate::apply_durations(
+            status,
+            &self.durations,
+            self.strategy,
+            Utc::now().timestamp(),
+        );
+    }
+}
+
+async fn resolve_workflow(
+    fetcher: &Fetcher,
+    token: &str,
+    pr: &PrReference,
+    workflow: &str,
+) -> eyre::Result<u64> {
+    if let Ok(id) = workflow.parse() {
+        return Ok(id);
+    }
+
+    let workflows = fetcher
+        .fetch_workflows(token, &pr.owner, &pr.repo)
+        .await
+        .wrap_err("fetching workflows to resolve workflow name")?;
+    workflows
+        .iter()
+        .find(|w| {
+            w.name == workflow
+                || w.filename == workflow
+                || w.filename.rsplit('/').next() == Some(workflow)
+        })
+        .map(|w| w.id)
+        .ok_or_else(|| eyre::eyre!("no workflow named '{workflow}' in {}/{}", pr.owner, pr.repo))
+}
+
+fn render(pr: &PrReference, status: &Pr) -> String {
+    const WIDTH: u64 = 30;
+    let filled = (WIDTH * status.num_complete_steps)
+        .checked_div(status.num_steps)
+        .unwrap_or(0);
+    let bar: String = (0..WIDTH)
+        .map(|i| if i < filled { '#' } else { '-' })
+        .collect();
+    let description = match status.status {
+        Status::Queued => "queued".to_string(),
+        Status::Waiting => "waiting".to_string(),
+        Status::InProgress(progress) => format!("{:.0}%", progress * 100.0),
+        other => format!("{other:?}").to_lowercase(),
+    };
+    format!(
+        "{}/{}#{} [{bar}] {}/{} steps {description}",
+        pr.owner, pr.repo, pr.number, status.num_complete_steps, status.num_steps
+    )
+}
+
+/// Exit code for a finished run, `None` if the run is still going
+fn exit_code(status: Status) -> Option<i32> {
+    match status {
+        Status::Queued | Status::Waiting | Status::InProgress(_) => None,
+        Status::Succeeded | Status::Skipped | Status::Neutral => Some(0),
+        Status::Failed | Status::TimedOut | Status::StartupFailure => Some(1),
+        Status::Cancelled => Some(2),
+        Status::ActionRequired | Status::Stale | Status::Unknown => Some(3),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fetcher::{Pr, Status, WorkflowStatus},
+        github::{RunConclusion, RunJob, RunStatus},
+    };
+
+    use super::{
+        Cli, Command, PrOutput, PrReference, StatusLine, StatusResult, STATUS_SCHEMA_VERSION,
+    };
+
+    #[test]
+    fn parse_pr_reference() {
+        let pr: PrReference = "simonrw/gh-ci-watch#123".parse().unwrap();
+        assert_eq!(
+            pr,
+            PrReference {
+                owner: "simonrw".into(),
+                repo: "gh-ci-watch".into(),
+                number: 123,
+            }
+        );
+
+        assert!("simonrw/gh-ci-watch".parse::<PrReference>().is_err());
+        assert!("gh-ci-watch#123".parse::<PrReference>().is_err());
+        assert!("simonrw/gh-ci-watch#abc".parse::<PrReference>().is_err());
+    }
+
+    #[test]
+    fn launcher_arguments_start_the_desktop_app() {
+        assert!(Cli::parse_command(["app"]).unwrap().is_none());
+        assert!(Cli::parse_command(["app", "-psn_0_12345"])
+            .unwrap()
+            .is_none());
+        assert!(Cli::parse_command(["app", "/home/user/file.txt"])
+            .unwrap()
+            .is_none());
+
+        let command = Cli::parse_command(["app", "watch", "simonrw/gh-ci-watch#123"]).unwrap();
+        assert!(matches!(command, Some(Command::Watch { .. })));
+        // a subcommand with missing arguments is still reported
+        assert!(Cli::parse_command(["app", "watch"]).is_err());
+        assert!(Cli::parse_command(["app", "--help"]).is_err());
+    }
+
+    #[test]
+    fn rejects_zero_interval() {
+        let args = ["app", "watch", "simonrw/gh-ci-watch#123", "--interval"];
+        assert!(Cli::parse_command(args.into_iter().chain(["0"])).is_err());
+        let command = Cli::parse_command(args.into_iter().chain(["1"])).unwrap();
+        assert!(matches!(
+            command,
+            Some(Command::Watch {
+                interval: Some(1),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn ndjson_line_is_flat() {
+        let result = StatusResult {
+            owner: "simonrw".into(),
+            repo: "gh-ci-watch".into(),
+            number: 123,
+            pr: None,
+            error: Some("not found".into()),
+        };
+        let line = StatusLine {
+            schema_version: STATUS_SCHEMA_VERSION,
+            result: &result,
+        };
+        let value = serde_json::to_value(&line).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "schema_version": 1,
+                "owner": "simonrw",
+                "repo": "gh-ci-watch",
+                "number": 123,
+                "pr": null,
+                "error": "not found",
+            })
+        );
+    }
+
+    #[test]
+    fn pr_output_schema() {
+        let job = RunJob {
+            id: 1000,
+            name: "test".into(),
+            status: RunStatus::Completed,
+            conclusion: Some(RunConclusion::Success),
+            created_at: None,
+            started_at: "2024-11-20T10:00:00Z".parse().unwrap(),
+            completed_at: Some("2024-11-20T10:05:00Z".parse().unwrap()),
+            steps: Vec::new(),
+        };
+        let workflow = WorkflowStatus::example(100, Status::Succeeded, vec![job]);
+        let pr = PrOutput::from(Pr::example(Status::Succeeded, vec![workflow]));
+
+        // changing this means bumping `STATUS_SCHEMA_VERSION` unless fields were only added
+        assert_eq!(
+            serde_json::to_value(&pr).unwrap(),
+            serde_json::json!({
+                "status": "Succeeded",
+                "state": "open",
+                "closed_at": null,
+                "title": "My PR",
+                "branch": "my-branch",
+                "description": "",
+                "num_steps": 0,
+                "num_complete_steps": 0,
+                "pr_url": "",
+                "run_url": "",
+                "workflows": [{
+                    "workflow_id": 10,
+                    "run_id": 100,
+                    "name": "ci",
+                    "status": "Succeeded",
+                    "num_steps": 0,
+                    "num_complete_steps": 0,
+                    "run_url": "",
+                    "jobs": [{
+                        "id": 1000,
+                        "name": "test",
+                        "status": "completed",
+                        "conclusion": "success",
+                        "started_at": "2024-11-20T10:00:00Z",
+                        "completed_at": "2024-11-20T10:05:00Z",
+                        "steps": [],
+                    }],
+                }],
+                "estimated_progress": null,
+                "estimated_completion": null,
+            })
+        );
+    }
+}
@@ -1,30 +1,151 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use color_eyre::eyre::{self, Context};
 use serde::{Deserialize, Serialize};
 
+use crate::{github::MAX_PER_PAGE, retry::RetryPolicy};
+
 #[derive(Serialize, Deserialize, Debug)]
+#[serde(default)]
 pub struct AppConfig {
     pub enable_sentry: bool,
+    /// How often the background watcher polls GitHub for watched PRs
+    pub poll_interval_secs: u64,
+    /// GitHub instances to monitor in addition to github.com
+    pub hosts: Vec<HostConfig>,
+    /// How requests that fail with network errors or server errors are retried
+    pub retry: RetryPolicy,
+    /// Number of items requested per page from list endpoints, at most 100
+    pub per_page: u32,
+    /// Client ID used to log in to github.com when it is not one of the `hosts`, see
+    /// [`HostConfig::oauth_client_id`]
+    pub oauth_client_id: Option<String>,
+    /// Automatically watch the open PRs of the logged in user
+    pub discovery: DiscoveryConfig,
+    /// How long merged or closed PRs keep being shown before they are archived and no longer
+    /// polled
+    pub archive_after_secs: u64,
+    /// How the progress of running workflows is calculated
+    pub progress: ProgressStrategy,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             enable_sentry: true,
+            poll_interval_secs: 10,
+            hosts: Vec::new(),
+            retry: RetryPolicy::default(),
+            per_page: MAX_PER_PAGE,
+            oauth_client_id: None,
+            discovery: DiscoveryConfig::default(),
+            archive_after_secs: DEFAULT_ARCHIVE_AFTER_SECS,
+            progress: ProgressStrategy::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressStrategy {
+    /// The fraction of steps that have completed, every step counting the same
+    #[default]
+    Steps,
+    /// Weight each step by how long it takes, from its timestamps and past runs
+    Duration,
+}
+
+/// Watch every open PR authored by the logged in user, found with the search API
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    pub enabled: bool,
+    /// Only PRs in repositories of these organizations or users
+    pub orgs: Vec<String>,
+    /// Only PRs in these repositories, e.g. `owner/repo`
+    pub repos: Vec<String>,
+    /// How often to search, the search API has a much smaller rate limit than the rest of the API
+    pub interval_secs: u64,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            orgs: Vec::new(),
+            repos: Vec::new(),
+            interval_secs: 300,
+        }
+    }
+}
+
+pub const DEFAULT_ARCHIVE_AFTER_SECS: u64 = 10 * 60;
+
+/// Name of the public GitHub host
+pub const DEFAULT_HOST: &str = "github.com";
+
+/// A GitHub instance, either github.com or a GitHub Enterprise Server
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HostConfig {
+    /// Name watched PRs use to refer to this host, usually its hostname
+    pub name: String,
+    /// e.g. `https://github.example.com/api/v3`
+    pub api_base_url: String,
+    /// e.g. `https://github.example.com`
+    pub web_base_url: String,
+    /// PEM encoded certificates to trust in addition to the system roots
+    pub ca_bundle: Option<PathBuf>,
+    /// Client ID of an OAuth app on this host with the device flow enabled, used to log in
+    /// without copying a token
+    #[serde(default)]
+    pub oauth_client_id: Option<String>,
+}
+
+impl HostConfig {
+    pub fn github() -> Self {
+        Self {
+            name: DEFAULT_HOST.to_string(),
+            api_base_url: "https://api.github.com".to_string(),
+            web_base_url: "https://github.com".to_string(),
+            ca_bundle: None,
+            oauth_client_id: None,
         }
     }
 }
 
+/// Directory holding the config file and any other state the app persists
+pub fn config_dir() -> eyre::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|p| p.join("gh-actions-monitor"))
+        .ok_or(eyre::eyre!("no XDG config path defined"))
+}
+
+/// Directory holding data the app collects, such as the history of watched PRs
+pub fn data_dir() -> eyre::Result<PathBuf> {
+    dirs::data_dir()
+        .map(|p| p.join("gh-actions-monitor"))
+        .ok_or(eyre::eyre!("no XDG data path defined"))
+}
+
 impl AppConfig {
     pub fn from_default_path() -> eyre::Result<Self> {
-        let config_dir = dirs::config_dir()
-            .map(|p| p.join("gh-actions-monitor"))
-            .ok_or(eyre::eyre!("no XDG config path defined"))?;
-        let config_file = config_dir.join("config.toml");
+        let config_file = config_dir()?.join("config.toml");
         Self::from_path(config_file)
     }
 
+    /// Every configured host, including github.com unless it has been overridden
+    pub fn hosts(&self) -> Vec<HostConfig> {
+        let mut hosts = self.hosts.clone();
+        if !hosts.iter().any(|h| h.name == DEFAULT_HOST) {
+            let github = HostConfig {
+                oauth_client_id: self.oauth_client_id.clone(),
+                ..HostConfig::github()
+            };
+            hosts.insert(0, github);
+        }
+        hosts
+    }
+
     pub fn from_path(path: impl AsRef<Path>) -> eyre::Result<Self> {
         let path = path.as_ref();
         let contents = std::fs::read_to_string(path)
@@ -33,3 +154,73 @@ impl AppConfig {
         Ok(config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{AppConfig, HostConfig, DEFAULT_HOST};
+
+    #[test]
+    fn parse_enterprise_hosts() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            [[hosts]]
+            name = "github.example.com"
+            api_base_url = "https://github.example.com/api/v3"
+            web_base_url = "https://github.example.com"
+            ca_bundle = "/etc/ssl/example.pem"
+            "#,
+        )
+        .unwrap();
+        assert!(config.enable_sentry);
+
+        let hosts = config.hosts();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0], HostConfig::github());
+        assert_eq!(hosts[1].name, "github.example.com");
+        assert_eq!(
+            hosts[1].ca_bundle,
+            Some(PathBuf::from("/etc/ssl/example.pem"))
+        );
+    }
+
+    #[test]
+    fn oauth_client_id_per_host() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            oauth_client_id = "github-client"
+
+            [[hosts]]
+            name = "github.example.com"
+            api_base_url = "https://github.example.com/api/v3"
+            web_base_url = "https://github.example.com"
+            oauth_client_id = "enterprise-client"
+            "#,
+        )
+        .unwrap();
+        let hosts = config.hosts();
+        assert_eq!(hosts[0].oauth_client_id.as_deref(), Some("github-client"));
+        assert_eq!(
+            hosts[1].oauth_client_id.as_deref(),
+            Some("enterprise-client")
+        );
+    }
+
+    #[test]
+    fn github_can_be_overridden() {
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                name: DEFAULT_HOST.into(),
+                api_base_url: "http://localhost:8080".into(),
+                web_base_url: "http://localhost:8080".into(),
+                ca_bundle: None,
+                oauth_client_id: None,
+            }],
+            ..Default::default()
+        };
+        let hosts = config.hosts();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].api_base_url, "http://localhost:8080");
+    }
+}
@@ -0,0 +1,220 @@
+use serde::Serialize;
+
+use crate::rate_limit;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors talking to GitHub, serialized for the frontend as an object tagged by `kind`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Error {
+    /// The token was rejected, usually because it has expired or been revoked
+    #[error("GitHub rejected the {}, it may have expired or been revoked", token_name(.credential))]
+    Unauthorized {
+        /// Name of the stored credential the token came from
+        credential: Option<String>,
+    },
+
+    /// The token is valid but lacks a permission the request needs
+    #[error("the {} is missing a permission{}", token_name(.credential), required_suffix(.required))]
+    Forbidden {
+        /// Permission or scope GitHub reported as required, e.g. `actions=read`
+        required: Option<String>,
+        credential: Option<String>,
+    },
+
+    /// GitHub also reports private resources the token cannot see as not found
+    #[error("{resource} not found, or the {} cannot access it", token_name(.credential))]
+    NotFound {
+        resource: String,
+        credential: Option<String>,
+    },
+
+    #[error("rate limited until {}", rate_limit::format_time(*.until))]
+    RateLimited {
+        /// Unix timestamp (in seconds) at which requests may be sent again
+        until: u64,
+    },
+
+    #[error("network error: {message}")]
+    Network { message: String },
+
+    /// Any other unsuccessful response
+    #[error("unexpected {status} response from GitHub")]
+    Status { status: u16 },
+
+    #[error("error decoding response: {message}")]
+    Decode { message: String },
+
+    /// No workflow has run against the head commit of the PR
+    #[error("{}", no_runs_message(.head_sha, *.previous_runs))]
+    NoRuns {
+        head_sha: String,
+        /// Whether earlier commits on the branch have runs, i.e. a run is likely still to come
+        previous_runs: bool,
+    },
+
+    #[error("'{input}' is not a PR, workflow run or branch URL, or `owner/repo#number`")]
+    InvalidReference { input: String },
+
+    #[error("host '{host}' is not configured")]
+    UnknownHost { host: String },
+
+    #[error("no token stored for {}{host}, log in first", owner_prefix(.owner))]
+    NoToken {
+        host: String,
+        /// Owner of the repository a credential was needed for
+        owner: Option<String>,
+    },
+
+    #[error(
+        "logging in with GitHub is not configured for this host, set its `oauth_client_id` in the config file"
+    )]
+    DeviceLoginUnavailable,
+
+    /// The device code expired before the user entered it, or no login was started
+    #[error("the login code has expired, start again")]
+    DeviceLoginExpired,
+
+    #[error("the login was cancelled")]
+    AccessDenied,
+
+    /// Any other error from the OAuth endpoints
+    #[serde(rename = "oauth")]
+    #[error("error logging in: {}", description.as_deref().unwrap_or(error))]
+    OAuth {
+        error: String,
+        description: Option<String>,
+    },
+
+    /// Reading or writing the token store failed
+    #[error("error storing token: {message}")]
+    Storage { message: String },
+}
+
+impl Error {
+    pub fn network(error: &reqwest::Error) -> Self {
+        // reqwest hides the interesting part (e.g. "connection refused") in the source chain
+        let mut message = error.to_string();
+        let mut source = std::error::Error::source(error);
+        while let Some(e) = source {
+            message.push_str(": ");
+            message.push_str(&e.to_string());
+            source = e.source();
+        }
+        Self::Network { message }
+    }
+
+    /// Record which credential the token of a rejected request came from
+    pub fn for_credential(mut self, name: &str) -> Self {
+        match &mut self {
+            Self::Unauthorized { credential }
+            | Self::Forbidden { credential, .. }
+            | Self::NotFound { credential, .. } => *credential = Some(name.to_string()),
+            _ => {}
+        }
+        self
+    }
+
+    pub fn storage(error: &color_eyre::eyre::Report) -> Self {
+        Self::Storage {
+            message: format!("{error:#}"),
+        }
+    }
+
+    pub fn decode(error: &serde_json::Error) -> Self {
+        Self::Decode {
+            message: error.to_string(),
+        }
+    }
+
+    /// Whether trying again later may succeed without the user doing anything
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Network { .. } | Self::RateLimited { .. } => true,
+            Self::Status { status } => *status >= 500,
+            // a run for the latest commit has usually just not been created yet
+            Self::NoRuns { previous_runs, .. } => *previous_runs,
+            _ => false,
+        }
+    }
+}
+
+fn token_name(credential: &Option<String>) -> String {
+    match credential {
+        Some(credential) => format!("token '{credential}'"),
+        None => "token".to_string(),
+    }
+}
+
+fn owner_prefix(owner: &Option<String>) -> String {
+    match owner {
+        Some(owner) => format!("{owner} on "),
+        None => String::new(),
+    }
+}
+
+fn required_suffix(required: &Option<String>) -> String {
+    match required {
+        Some(required) => format!(", it needs `{required}`"),
+        None => String::new(),
+    }
+}
+
+fn no_runs_message(head_sha: &str, previous_runs: bool) -> String {
+    if previous_runs {
+        format!("no workflow run yet for latest commit {head_sha}")
+    } else {
+        format!("no workflow runs found for commit {head_sha}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test]
+    fn serializes_as_tagged_object() {
+        let error = Error::Forbidden {
+            required: Some("actions=read".into()),
+            credential: None,
+        };
+        assert_eq!(
+            error.to_string(),
+            "the token is missing a permission, it needs `actions=read`"
+        );
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "kind": "forbidden", "required": "actions=read", "credential": null })
+        );
+        assert_eq!(
+            serde_json::to_value(Error::Unauthorized { credential: None }).unwrap(),
+            serde_json::json!({ "kind": "unauthorized", "credential": null })
+        );
+    }
+
+    #[test]
+    fn names_the_failing_credential() {
+        let error = Error::Unauthorized { credential: None }.for_credential("work");
+        assert_eq!(
+            error.to_string(),
+            "GitHub rejected the token 'work', it may have expired or been revoked"
+        );
+        assert_eq!(
+            Error::RateLimited { until: 0 }.for_credential("work"),
+            Error::RateLimited { until: 0 }
+        );
+    }
+
+    #[test]
+    fn only_transient_errors_are_worth_retrying() {
+        assert!(Error::RateLimited { until: 0 }.is_transient());
+        assert!(Error::Status { status: 502 }.is_transient());
+        assert!(!Error::Status { status: 422 }.is_transient());
+        assert!(!Error::Unauthorized { credential: None }.is_transient());
+        assert!(!Error::UnknownHost {
+            host: "example.com".into()
+        }
+        .is_transient());
+    }
+}
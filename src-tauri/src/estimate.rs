@@ -0,0 +1,414 @@
+use std::{collections::HashMap, hash::Hash};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::ProgressStrategy,
+    fetcher::{Pr, Status, WorkflowStatus},
+    github::{JobStep, RunConclusion, RunJob, RunStatus},
+};
+
+/// Typical duration in seconds of each job of a workflow, keyed by job name
+pub type JobDurations = HashMap<String, u64>;
+
+/// Typical duration in seconds of each step of a workflow, keyed by job and step name
+pub type StepDurations = HashMap<(String, String), u64>;
+
+/// Typical job and step durations of several workflows, keyed by workflow id
+pub type WorkflowDurations = HashMap<u64, (JobDurations, StepDurations)>;
+
+/// Median of the durations of each job or step in past runs
+pub fn median_durations<K: Hash + Eq>(
+    samples: impl IntoIterator<Item = (K, u64)>,
+) -> HashMap<K, u64> {
+    let mut by_key: HashMap<K, Vec<u64>> = HashMap::new();
+    for (key, duration) in samples {
+        by_key.entry(key).or_default().push(duration);
+    }
+    by_key
+        .into_iter()
+        .map(|(key, mut durations)| {
+            durations.sort_unstable();
+            (key, durations[durations.len() / 2])
+        })
+        .collect()
+}
+
+/// Median duration of each job that succeeded in `jobs`, which may span several runs
+pub fn job_durations(jobs: &[RunJob]) -> JobDurations {
+    median_durations(
+        jobs.iter()
+            .filter(|job| job.conclusion == Some(RunConclusion::Success))
+            .filter_map(|job| {
+                let duration = job.duration()?.num_seconds().max(0) as u64;
+                Some((job.name.clone(), duration))
+            }),
+    )
+}
+
+/// Median duration of each step of the jobs that succeeded in `jobs`
+pub fn step_durations(jobs: &[RunJob]) -> StepDurations {
+    median_durations(
+        jobs.iter()
+            .filter(|job| job.conclusion == Some(RunConclusion::Success))
+            .flat_map(|job| job.steps.iter().map(move |step| (job, step)))
+            .filter_map(|(job, step)| {
+                let duration = step.completed_at? - step.started_at?;
+                let key = (job.name.clone(), step.name.clone());
+                Some((key, duration.num_seconds().max(0) as u64))
+            }),
+    )
+}
+
+/// Predicted progress of a run, from how long its jobs took in the past
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Seconds of work expected in total
+    pub expected_secs: u64,
+    /// Seconds of that work already done
+    pub done_secs: u64,
+    /// Unix timestamp (in seconds) the run is expected to complete at
+    pub completes_at: i64,
+}
+
+impl Estimate {
+    pub fn progress(&self) -> f32 {
+        if self.expected_secs == 0 {
+            return 1.0;
+        }
+        self.done_secs as f32 / self.expected_secs as f32
+    }
+}
+
+/// Estimate the progress of a run, `None` if none of its jobs have run before
+///
+/// Jobs without any history are expected to take as long as the average job that has one.
+/// The remaining jobs are assumed to run in parallel, as they do when runners are available.
+pub fn estimate(jobs: &[RunJob], durations: &JobDurations, now: i64) -> Option<Estimate> {
+    let known: Vec<u64> = jobs
+        .iter()
+        .filter_map(|job| durations.get(&job.name).copied())
+        .collect();
+    if known.is_empty() {
+        return None;
+    }
+    let fallback = known.iter().sum::<u64>() / known.len() as u64;
+
+    let mut expected_secs = 0;
+    let mut done_secs = 0;
+    let mut remaining_secs = 0;
+    for job in jobs {
+        let mut expected = durations.get(&job.name).copied().unwrap_or(fallback);
+        match job.status {
+            RunStatus::Completed => done_secs += expected,
+            RunStatus::InProgress => {
+                let elapsed = (now - job.started_at.timestamp()).max(0) as u64;
+                // a job running over time is nearly done, rather than more than done
+                expected = expected.max(elapsed);
+                done_secs += elapsed;
+                remaining_secs = remaining_secs.max(expected - elapsed);
+            }
+            _ => remaining_secs = remaining_secs.max(expected),
+        }
+        expected_secs += expected;
+    }
+
+    Some(Estimate {
+        expected_secs,
+        done_secs,
+        completes_at: now + remaining_secs as i64,
+    })
+}
+
+/// Seconds of work done and expected in total for a set of jobs
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WeightedProgress {
+    pub done_secs: u64,
+    pub total_secs: u64,
+}
+
+impl WeightedProgress {
+    pub fn fraction(&self) -> f32 {
+        if self.total_secs == 0 {
+            return 0.0;
+        }
+        self.done_secs as f32 / self.total_secs as f32
+    }
+}
+
+impl std::ops::Add for WeightedProgress {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            done_secs: self.done_secs + other.done_secs,
+            total_secs: self.total_secs + other.total_secs,
+        }
+    }
+}
+
+/// Progress of a run with every step weighted by how long it takes
+///
+/// Completed steps count for as long as they actually took, the running step for as long as it
+/// usually takes (or has taken so far if that is longer) and pending steps for as long as they
+/// usually take. Steps that have never run before count for the average step.
+pub fn weighted_progress(jobs: &[RunJob], durations: &StepDurations, now: i64) -> WeightedProgress {
+    let elapsed = |started: Option<DateTime<Utc>>, completed: Option<Option<DateTime<Utc>>>| {
+        let started = started?.timestamp();
+        let completed = match completed {
+            Some(completed) => completed?.timestamp(),
+            None => now,
+        };
+        Some((completed - started).max(0) as u64)
+    };
+    let typical = |job: &RunJob, step: &JobStep| {
+        durations
+            .get(&(job.name.clone(), step.name.clone()))
+            .copied()
+    };
+
+    // steps with nothing to go on count as the average of the others
+    let known: Vec<u64> = jobs
+        .iter()
+        .flat_map(|job| job.steps.iter().map(move |step| (job, step)))
+        .filter_map(|(job, step)| match step.status {
+            RunStatus::Completed => elapsed(step.started_at, Some(step.completed_at)),
+            _ => typical(job, step),
+        })
+        .collect();
+    let fallback = match known.len() {
+        0 => 1,
+        n => (known.iter().sum::<u64>() / n as u64).max(1),
+    };
+
+    let mut progress = WeightedProgress::default();
+    for job in jobs {
+        for step in &job.steps {
+            let expected = typical(job, step).unwrap_or(fallback);
+            let (done, total) = match step.status {
+                _ if job.status == RunStatus::Completed || step.status == RunStatus::Completed => {
+                    let took =
+                        elapsed(step.started_at, Some(step.completed_at)).unwrap_or(expected);
+                    (took, took)
+                }
+                RunStatus::InProgress => {
+                    let so_far = elapsed(step.started_at, None).unwrap_or_default();
+                    // a step running over time is nearly done, rather than more than done
+                    (so_far, expected.max(so_far))
+                }
+                _ => (0, expected),
+            };
+            progress.done_secs += done;
+            progress.total_secs += total;
+        }
+    }
+    progress
+}
+
+/// Fill in the estimated progress and completion time of a PR that is still running
+///
+/// `durations` gives the past job durations of a workflow. The PR as a whole completes when
+/// its last workflow does.
+pub fn apply(pr: &mut Pr, durations: impl Fn(&WorkflowStatus) -> JobDurations, now: i64) {
+    let running = |w: &&WorkflowStatus| w.status.is_running();
+    let estimates: Option<Vec<Estimate>> = pr
+        .workflows
+        .iter()
+        .filter(running)
+        .map(|w| estimate(&w.jobs, &durations(w), now))
+        .collect();
+    let Some(estimates) = estimates.filter(|e| !e.is_empty()) else {
+        return;
+    };
+
+    // workflows that have finished are all done work
+    let finished: u64 = pr
+        .workflows
+        .iter()
+        .filter(|w| !running(w))
+        .map(|w| {
+            let durations = durations(w);
+            w.jobs
+                .iter()
+                .filter_map(|j| durations.get(&j.name))
+                .sum::<u64>()
+        })
+        .sum();
+    let total = Estimate {
+        expected_secs: estimates.iter().map(|e| e.expected_secs).sum::<u64>() + finished,
+        done_secs: estimates.iter().map(|e| e.done_secs).sum::<u64>() + finished,
+        completes_at: estimates
+            .iter()
+            .map(|e| e.completes_at)
+            .max()
+            .unwrap_or(now),
+    };
+    pr.estimated_progress = Some(total.progress());
+    pr.estimated_completion = Some(total.completes_at);
+}
+
+/// Fill in the estimates of a PR from the typical durations of its workflows
+///
+/// With [`ProgressStrategy::Duration`], the progress of running workflows is also weighted by
+/// how long each of their steps takes rather than counting steps.
+pub fn apply_durations(
+    pr: &mut Pr,
+    durations: &WorkflowDurations,
+    strategy: ProgressStrategy,
+    now: i64,
+) {
+    apply(
+        pr,
+        |workflow| {
+            durations
+                .get(&workflow.workflow_id)
+                .map(|(jobs, _)| jobs.clone())
+                .unwrap_or_default()
+        },
+        now,
+    );
+    if strategy != ProgressStrategy::Duration {
+        return;
+    }
+
+    let no_history = StepDurations::new();
+    let mut total = WeightedProgress::default();
+    for workflow in &mut pr.workflows {
+        let steps = durations
+            .get(&workflow.workflow_id)
+            .map_or(&no_history, |(_, steps)| steps);
+        let progress = weighted_progress(&workflow.jobs, steps, now);
+        if let Status::InProgress(_) = workflow.status {
+            workflow.status = Status::InProgress(progress.fraction());
+        }
+        total = total + progress;
+    }
+    if let Status::InProgress(_) = pr.status {
+        pr.status = Status::InProgress(total.fraction());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+
+    use crate::{
+        config::ProgressStrategy,
+        fetcher::{Pr, Status, WorkflowStatus},
+        github::{GetRunJobsResponse, RunJob},
+    };
+
+    use super::{
+        apply_durations, estimate, job_durations, median_durations, step_durations,
+        weighted_progress, JobDurations, StepDurations, WorkflowDurations,
+    };
+
+    fn jobs(path: &str) -> Vec<RunJob> {
+        let s = std::fs::read_to_string(path).unwrap();
+        let GetRunJobsResponse { jobs } = serde_json::from_str(&s).unwrap();
+        jobs
+    }
+
+    /// Jobs of the completed runs in `testdata`
+    fn history() -> Vec<RunJob> {
+        [
+            "testdata/completed-jobs.json",
+            "testdata/completed-jobs-2.json",
+        ]
+        .into_iter()
+        .flat_map(jobs)
+        .collect()
+    }
+
+    #[test]
+    fn takes_the_median() {
+        let durations = median_durations([
+            ("lint".to_string(), 30),
+            ("test".to_string(), 100),
+            ("test".to_string(), 600),
+            ("test".to_string(), 120),
+        ]);
+        assert_eq!(durations["lint"], 30);
+        assert_eq!(durations["test"], 120);
+    }
+
+    #[test]
+    fn clamps_negative_durations() {
+        let mut jobs = jobs("testdata/completed-jobs.json");
+        jobs.truncate(1);
+        // skewed clocks put the end before the start
+        jobs[0].completed_at = Some(jobs[0].started_at - chrono::Duration::seconds(30));
+        assert_eq!(job_durations(&jobs)[&jobs[0].name], 0);
+    }
+
+    #[test]
+    fn weights_progress_by_duration() {
+        let jobs = jobs("testdata/in-progress-jobs.json");
+        // 2024-11-20T10:22:51Z, three minutes into `cargo test`
+        let now = 1732098171;
+        let estimate = estimate(&jobs, &job_durations(&history()), now).unwrap();
+
+        // 11 of 16 steps are done, but most of the time is spent testing
+        assert_eq!(estimate.expected_secs, 58 + 659);
+        assert_eq!(estimate.done_secs, 58 + 371);
+        assert_abs_diff_eq!(estimate.progress(), 0.598, epsilon = 0.001);
+        // the test job usually takes 659s, and has been running for 371s
+        assert_eq!(estimate.completes_at, now + 288);
+    }
+
+    #[test]
+    fn weights_steps_by_duration() {
+        let jobs = jobs("testdata/in-progress-jobs.json");
+        let history = step_durations(&history());
+        // three minutes into `cargo test`
+        let now = 1732098171;
+
+        let progress = weighted_progress(&jobs, &history, now);
+        // 245s of completed steps and 180s of `cargo test`
+        assert_eq!(progress.done_secs, 425);
+        // `cargo test` usually takes 437s, and the steps after it another 23s
+        assert_eq!(progress.total_secs, 245 + 437 + 23);
+        assert_abs_diff_eq!(progress.fraction(), 0.603, epsilon = 0.001);
+
+        // without any history, steps that have not run count as the average completed step, 22s
+        let progress = weighted_progress(&jobs, &StepDurations::new(), now);
+        assert_eq!(progress.done_secs, 425);
+        assert_eq!(progress.total_secs, 425 + 4 * 22);
+    }
+
+    #[test]
+    fn duration_strategy_replaces_step_counts() {
+        let running = Status::InProgress(11.0 / 16.0);
+        let workflow = WorkflowStatus::example(1, running, jobs("testdata/in-progress-jobs.json"));
+        let pr = Pr::example(running, vec![workflow]);
+        let durations = WorkflowDurations::from([(
+            10,
+            (job_durations(&history()), step_durations(&history())),
+        )]);
+        let now = 1732098171;
+
+        let mut by_steps = pr.clone();
+        apply_durations(&mut by_steps, &durations, ProgressStrategy::Steps, now);
+        assert_eq!(by_steps.status, running);
+        assert_eq!(by_steps.estimated_completion, Some(now + 288));
+
+        let mut by_duration = pr;
+        apply_durations(
+            &mut by_duration,
+            &durations,
+            ProgressStrategy::Duration,
+            now,
+        );
+        assert_eq!(by_duration.status, by_duration.workflows[0].status);
+        let Status::InProgress(progress) = by_duration.status else {
+            panic!("expected to be in progress, got {:?}", by_duration.status);
+        };
+        assert_abs_diff_eq!(progress, 0.603, epsilon = 0.001);
+    }
+
+    #[test]
+    fn no_history_no_estimate() {
+        let jobs = jobs("testdata/in-progress-jobs.json");
+        assert_eq!(estimate(&jobs, &JobDurations::new(), 0), None);
+    }
+}
@@ -1,25 +1,117 @@
-use std::sync::Arc;
+use std::{
+    collections::{btree_map::Entry, BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
 
 use crate::{
-    config::AppConfig,
+    config::{AppConfig, HostConfig},
+    error::{self, Error},
+    estimate::{self, JobDurations, StepDurations},
     github::{
-        GetPullRequestResponse, GetRunJobsResponse, GetWorkflowRunsQueryArgs,
-        GetWorkflowRunsResponse, GetWorkflowsResponse, GitHubClient, RunJob, WorkflowDetails,
+        DeviceCode, DevicePoll, GetPullRequestResponse, GetRunJobsResponse,
+        GetWorkflowRunsQueryArgs, GetWorkflowRunsResponse, GetWorkflowsResponse, GitHubClient,
+        PullRequestState, PullRequestSummary, RunConclusion, RunJob, RunStatus,
+        SearchIssuesResponse, WorkflowDetails, WorkflowRun,
     },
+    rate_limit::{RateLimit, TokenBudget},
+    reference::GitHubReference,
+    token::{self, TokenReport},
 };
-use color_eyre::eyre::{self, Context};
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+/// Number of successful runs fetched to estimate durations from, each costs a request
+const PAST_RUNS: usize = 3;
 
 pub struct Fetcher {
     client: GitHubClient,
 }
 
+/// A [`Fetcher`] for every configured GitHub host, keyed by host name
+pub struct Fetchers {
+    fetchers: HashMap<String, Arc<Fetcher>>,
+}
+
+impl Fetchers {
+    /// A fetcher for every host that can be set up
+    ///
+    /// Hosts that fail to set up, e.g. because their CA bundle cannot be read, are logged and
+    /// skipped so the other hosts can still be watched.
+    pub fn from_config(app_config: Arc<AppConfig>) -> Self {
+        let mut fetchers = HashMap::new();
+        for host in app_config.hosts() {
+            match Fetcher::for_host(&host, Arc::clone(&app_config)) {
+                Ok(fetcher) => {
+                    fetchers.insert(host.name, Arc::new(fetcher));
+                }
+                Err(e) => {
+                    tracing::error!(host = %host.name, error = ?e, "error creating client for host, skipping it");
+                }
+            }
+        }
+        Self { fetchers }
+    }
+
+    pub fn get(&self, host: &str) -> error::Result<&Arc<Fetcher>> {
+        self.fetchers.get(host).ok_or_else(|| Error::UnknownHost {
+            host: host.to_string(),
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Arc<Fetcher>)> {
+        self.fetchers.iter()
+    }
+}
+
 impl Fetcher {
     pub fn new(base_url: impl Into<String>, app_config: Arc<AppConfig>) -> Self {
         let client = GitHubClient::new(base_url, app_config);
         Self { client }
     }
 
+    pub fn for_host(host: &HostConfig, app_config: Arc<AppConfig>) -> eyre::Result<Self> {
+        let client = GitHubClient::for_host(host, app_config)?;
+        Ok(Self { client })
+    }
+
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.client.rate_limit()
+    }
+
+    pub fn budgets(&self) -> HashMap<u64, TokenBudget> {
+        self.client.budgets()
+    }
+
+    /// Unix timestamp (in seconds) the token expires at, as of the last response to it
+    pub fn token_expiry(&self, token: impl AsRef<str>) -> Option<i64> {
+        self.client.token_expiry(token.as_ref())
+    }
+
+    /// Start logging in with the OAuth device flow
+    pub async fn start_device_login(&self) -> error::Result<DeviceCode> {
+        self.client.start_device_flow().await
+    }
+
+    pub async fn poll_device_login(&self, device_code: &str) -> error::Result<DevicePoll> {
+        self.client.poll_device_flow(device_code).await
+    }
+
+    /// Check a token, optionally checking its permissions against a sample repository
+    pub async fn validate_token(
+        &self,
+        token: impl AsRef<str>,
+        sample_repo: Option<(&str, &str)>,
+    ) -> error::Result<TokenReport> {
+        token::validate(
+            &self.client,
+            token.as_ref(),
+            sample_repo,
+            chrono::Utc::now().timestamp(),
+        )
+        .await
+    }
+
     pub async fn fetch(
         &self,
         token: impl AsRef<str>,
@@ -27,103 +119,142 @@ impl Fetcher {
         repo: impl AsRef<str>,
         workflow_id: u64,
         pr_number: u64,
-    ) -> eyre::Result<Pr> {
+    ) -> error::Result<Pr> {
         let token = token.as_ref();
         let owner = owner.as_ref();
         let repo = repo.as_ref();
         let pr_info = self.fetch_pr_info(owner, repo, pr_number, token).await?;
 
-        // fetch workflow runs for branch
-        let GetWorkflowRunsResponse { mut workflow_runs } = self
-            .fetch_workflow_runs(owner, repo, workflow_id, pr_info.head.branch, token)
+        // fetch workflow runs for the current head commit
+        let workflow_runs = self
+            .fetch_workflow_runs(owner, repo, workflow_id, &pr_info.head.sha, token)
             .await?;
-        workflow_runs.sort_by_key(|k| k.run_number);
-        let Some(run) = workflow_runs.pop() else {
-            // TODO
-            eyre::bail!("no workflow runs found");
+        let Some(run) = workflow_runs
+            .into_iter()
+            .filter(|run| run.is_for_head(&pr_info.head))
+            .max_by_key(|run| run.run_number)
+        else {
+            // a PR closed before CI ran still needs reporting so it can be archived
+            if pr_info.state != PullRequestState::Open {
+                return Ok(Pr::without_runs(pr_info));
+            }
+            // distinguish between a workflow that has not yet been triggered for the latest
+            // push, and one that has never run for this PR
+            let previous_runs = self
+                .has_previous_runs(owner, repo, Some(workflow_id), &pr_info, token)
+                .await?;
+            return Err(Error::NoRuns {
+                head_sha: pr_info.head.sha,
+                previous_runs,
+            });
         };
 
         tracing::debug!(run_id = %run.id, "got latest run");
 
-        // DEBUG
-        // let mut f = std::fs::File::create("in-progress-jobs.json").unwrap();
-        // if let Err(e) = serde_json::to_writer_pretty(&mut f, &jobs) {
-        //     tracing::warn!(error = ?e, "error saving in-progress job JSON state");
-        // }
-        // get run jobs
-        tracing::debug!("fetching jobs for run");
-        let GetRunJobsResponse { jobs } = self
-            .fetch_run_jobs(owner, repo, run.id, token)
-            .await
-            .wrap_err("fetching run jobs")?;
-
-        let ProgressResult {
-            progress,
-            complete,
-            total,
-        } = calculate_progress(&jobs);
+        let workflow = self.fetch_workflow_status(owner, repo, run, token).await?;
 
         tracing::debug!("updating PR state");
-        let pr_result = match run.status.as_str() {
-            "completed" => match run.conclusion.as_deref() {
-                Some("failure") => {
-                    Pr {
-                        status: Status::Failed,
-                        title: pr_info.title,
-                        description: pr_info.description.unwrap_or_else(String::new),
-                        num_steps: total,
-                        num_complete_steps: complete,
-                        pr_url: pr_info.url,
-                        run_url: run.url,
+        let (state, closed_at) = PrState::from_response(&pr_info);
+        let pr_result = Pr {
+            status: workflow.status,
+            state,
+            closed_at,
+            title: pr_info.title,
+            branch: pr_info.head.branch,
+            description: pr_info.description.unwrap_or_default(),
+            num_steps: workflow.num_steps,
+            num_complete_steps: workflow.num_complete_steps,
+            pr_url: pr_info.url,
+            run_url: workflow.run_url.clone(),
+            timing: workflow.timing,
+            workflows: vec![workflow],
+            estimated_progress: None,
+            estimated_completion: None,
+        };
+
+        tracing::debug!(pr = %pr_number, status = ?pr_result, "PR result");
+
+        Ok(pr_result)
+    }
+
+    /// Fetch the status of every workflow that ran against the head commit of a PR
+    pub async fn fetch_pr_status(
+        &self,
+        token: impl AsRef<str>,
+        owner: impl AsRef<str>,
+        repo: impl AsRef<str>,
+        pr_number: u64,
+    ) -> error::Result<Pr> {
+        let token = token.as_ref();
+        let owner = owner.as_ref();
+        let repo = repo.as_ref();
+        let pr_info = self.fetch_pr_info(owner, repo, pr_number, token).await?;
+
+        let workflow_runs = self
+            .fetch_runs_for_commit(owner, repo, &pr_info.head.sha, token)
+            .await?;
+
+        // only keep the latest run of each workflow
+        let mut latest_runs: BTreeMap<u64, WorkflowRun> = BTreeMap::new();
+        for run in workflow_runs
+            .into_iter()
+            .filter(|run| run.is_for_head(&pr_info.head))
+        {
+            match latest_runs.entry(run.workflow_id) {
+                Entry::Occupied(mut entry) => {
+                    if run.run_number > entry.get().run_number {
+                        entry.insert(run);
                     }
-                    // tracing::debug!(before = ?pr.status, after = ?Status::Failed, "updating status");
-                    // pr.status = Status::Failed;
                 }
-                Some("success") => Pr {
-                    status: Status::Succeeded,
-                    title: pr_info.title,
-                    description: pr_info.description.unwrap_or_default(),
-                    num_steps: total,
-                    num_complete_steps: complete,
-                    pr_url: pr_info.url,
-                    run_url: run.url,
-                },
-                other => {
-                    todo!("unhandled combination of status: completed and conclusion: {other:?}")
-                }
-            },
-            "queued" => Pr {
-                status: Status::Queued,
-                title: pr_info.title,
-                description: pr_info.description.unwrap_or_default(),
-                num_steps: total,
-                num_complete_steps: complete,
-                pr_url: pr_info.url,
-                run_url: run.url,
-            },
-            "in_progress" => {
-                // get run jobs
-                let status = Status::InProgress(progress);
-                Pr {
-                    status,
-                    title: pr_info.title,
-                    description: pr_info.description.unwrap_or_default(),
-                    num_steps: total,
-                    num_complete_steps: complete,
-                    pr_url: pr_info.url,
-                    run_url: run.url,
+                Entry::Vacant(entry) => {
+                    entry.insert(run);
                 }
             }
-            "pending" => Pr {
-                status: Status::Queued,
-                title: pr_info.title,
-                description: pr_info.description.unwrap_or_default(),
-                num_steps: total,
-                num_complete_steps: complete,
-                pr_url: pr_info.url,
-                run_url: run.url,
-            },
-            other => todo!("unhandled status: {other}"),
+        }
+
+        if latest_runs.is_empty() {
+            if pr_info.state != PullRequestState::Open {
+                return Ok(Pr::without_runs(pr_info));
+            }
+            // CI has usually just not picked up the latest push yet
+            let previous_runs = self
+                .has_previous_runs(owner, repo, None, &pr_info, token)
+                .await?;
+            return Err(Error::NoRuns {
+                head_sha: pr_info.head.sha,
+                previous_runs,
+            });
+        }
+        tracing::debug!(num_workflows = %latest_runs.len(), "got latest runs");
+
+        let mut workflows = Vec::with_capacity(latest_runs.len());
+        for run in latest_runs.into_values() {
+            let workflow = self.fetch_workflow_status(owner, repo, run, token).await?;
+            workflows.push(workflow);
+        }
+
+        let num_steps = workflows.iter().map(|w| w.num_steps).sum();
+        let num_complete_steps = workflows.iter().map(|w| w.num_complete_steps).sum();
+        let statuses: Vec<Status> = workflows.iter().map(|w| w.status).collect();
+        let status = Status::combine(&statuses, step_fraction(num_complete_steps, num_steps));
+        let timings: Vec<Timing> = workflows.iter().map(|w| w.timing).collect();
+
+        let (state, closed_at) = PrState::from_response(&pr_info);
+        let pr_result = Pr {
+            status,
+            state,
+            closed_at,
+            title: pr_info.title,
+            branch: pr_info.head.branch,
+            description: pr_info.description.unwrap_or_default(),
+            num_steps,
+            num_complete_steps,
+            run_url: format!("{}/checks", pr_info.url),
+            pr_url: pr_info.url,
+            timing: Timing::combine(&timings),
+            workflows,
+            estimated_progress: None,
+            estimated_completion: None,
         };
 
         tracing::debug!(pr = %pr_number, status = ?pr_result, "PR result");
@@ -131,12 +262,42 @@ impl Fetcher {
         Ok(pr_result)
     }
 
+    async fn fetch_workflow_status(
+        &self,
+        owner: &str,
+        repo: &str,
+        run: WorkflowRun,
+        token: &str,
+    ) -> error::Result<WorkflowStatus> {
+        tracing::debug!(run_id = %run.id, "fetching jobs for run");
+        let jobs = self.fetch_run_jobs(owner, repo, run.id, token).await?;
+
+        let ProgressResult {
+            progress,
+            complete,
+            total,
+        } = calculate_progress(&jobs);
+
+        Ok(WorkflowStatus {
+            timing: Timing::of_run(&run, &jobs),
+            workflow_id: run.workflow_id,
+            run_id: run.id,
+            run_attempt: run.run_attempt,
+            name: run.name,
+            status: Status::from_run(run.status, run.conclusion, progress),
+            num_steps: total,
+            num_complete_steps: complete,
+            run_url: run.url,
+            jobs,
+        })
+    }
+
     pub async fn fetch_workflows(
         &self,
         token: impl AsRef<str>,
         owner: impl AsRef<str>,
         repo: impl AsRef<str>,
-    ) -> eyre::Result<Vec<WorkflowDetails>> {
+    ) -> error::Result<Vec<WorkflowDetails>> {
         let token = token.as_ref();
         let owner = owner.as_ref();
         let repo = repo.as_ref();
@@ -145,31 +306,193 @@ impl Fetcher {
         // requires `actions:read`
         match self
             .client
-            .get(
+            .get_paginated::<GetWorkflowsResponse, _>(
                 format!("/repos/{}/{}/actions/workflows", owner, repo),
                 token,
                 None::<()>,
             )
             .await
         {
-            Ok(GetWorkflowsResponse { workflows }) => {
+            Ok(workflows) => {
                 tracing::debug!(?workflows, "got workflows for repo");
                 Ok(workflows)
             }
             Err(e) => {
                 tracing::warn!(error = %e, "error fetching workflows");
-                eyre::bail!("error fetching workflows");
+                Err(e)
             }
         }
     }
 
+    /// Open PRs authored by the owner of the token, optionally limited to some
+    /// organizations or repositories
+    pub async fn search_my_prs(
+        &self,
+        token: impl AsRef<str>,
+        orgs: &[String],
+        repos: &[String],
+    ) -> error::Result<Vec<OpenPr>> {
+        let mut query = "is:pr is:open author:@me".to_string();
+        // scope qualifiers of the same search are combined with OR
+        for org in orgs {
+            query.push_str(&format!(" org:{org}"));
+        }
+        for repo in repos {
+            query.push_str(&format!(" repo:{repo}"));
+        }
+        tracing::debug!(%query, "searching for open prs");
+
+        let items = self
+            .client
+            .get_paginated::<SearchIssuesResponse, _>(
+                "/search/issues",
+                token.as_ref(),
+                Some([("q", query)]),
+            )
+            .await?;
+        let prs = items
+            .into_iter()
+            .filter_map(|item| {
+                let mut segments = item.repository_url.rsplit('/');
+                let repo = segments.next()?.to_string();
+                let owner = segments.next()?.to_string();
+                Some(OpenPr {
+                    owner,
+                    repo,
+                    number: item.number,
+                })
+            })
+            .collect();
+        Ok(prs)
+    }
+
+    /// Find the PR a pasted reference points at, along with the workflows that ran on it
+    ///
+    /// Runs and branches are mapped to the open PR for their branch.
+    pub async fn resolve_reference(
+        &self,
+        token: impl AsRef<str>,
+        reference: &GitHubReference,
+    ) -> error::Result<ReferencedPr> {
+        let token = token.as_ref();
+        let (owner, repo, pr_number, run_workflow_id) = match reference {
+            GitHubReference::PullRequest {
+                owner,
+                repo,
+                number,
+                ..
+            } => (owner, repo, *number, None),
+            GitHubReference::Run {
+                owner,
+                repo,
+                run_id,
+                ..
+            } => {
+                tracing::debug!(%run_id, "fetching workflow run");
+                let run: WorkflowRun = self
+                    .client
+                    .get(
+                        format!("/repos/{owner}/{repo}/actions/runs/{run_id}"),
+                        token,
+                        None::<()>,
+                    )
+                    .await?;
+                let pr_number = match run.pull_requests.first() {
+                    Some(pr) => pr.number,
+                    None => {
+                        // runs for PRs from forks do not list their PR
+                        let head_owner = run
+                            .head_repository
+                            .as_ref()
+                            .and_then(|r| r.full_name.split_once('/'))
+                            .map_or(owner.as_str(), |(head_owner, _)| head_owner);
+                        let branch = run.head_branch.as_deref().unwrap_or_default();
+                        self.fetch_pr_for_branch(owner, repo, head_owner, branch, token)
+                            .await?
+                    }
+                };
+                (owner, repo, pr_number, Some(run.workflow_id))
+            }
+            GitHubReference::Branch {
+                owner,
+                repo,
+                branch,
+                ..
+            } => {
+                let pr_number = self
+                    .fetch_pr_for_branch(owner, repo, owner, branch, token)
+                    .await?;
+                (owner, repo, pr_number, None)
+            }
+        };
+
+        let pr_info = self.fetch_pr_info(owner, repo, pr_number, token).await?;
+        let ran: BTreeSet<u64> = self
+            .fetch_runs_for_commit(owner, repo, &pr_info.head.sha, token)
+            .await?
+            .into_iter()
+            .filter(|run| run.is_for_head(&pr_info.head))
+            .map(|run| run.workflow_id)
+            .collect();
+        let workflows: Vec<WorkflowDetails> = self
+            .fetch_workflows(token, owner, repo)
+            .await?
+            .into_iter()
+            .filter(|w| ran.contains(&w.id))
+            .collect();
+
+        // a run URL picks its workflow, otherwise only pick one if there is no choice
+        let workflow_id = run_workflow_id.or(match workflows.as_slice() {
+            [workflow] => Some(workflow.id),
+            _ => None,
+        });
+        Ok(ReferencedPr {
+            owner: owner.clone(),
+            repo: repo.clone(),
+            pr_number,
+            title: pr_info.title,
+            workflow_id,
+            workflows,
+        })
+    }
+
+    /// The number of the open PR from `head_owner:branch`
+    async fn fetch_pr_for_branch(
+        &self,
+        owner: &str,
+        repo: &str,
+        head_owner: &str,
+        branch: &str,
+        token: &str,
+    ) -> error::Result<u64> {
+        tracing::debug!(%head_owner, %branch, "fetching pr for branch");
+        // requires `contents:read`
+        let prs: Vec<PullRequestSummary> = self
+            .client
+            .get(
+                format!("/repos/{owner}/{repo}/pulls"),
+                token,
+                Some([
+                    ("head", format!("{head_owner}:{branch}")),
+                    ("state", "open".to_string()),
+                ]),
+            )
+            .await?;
+        prs.first()
+            .map(|pr| pr.number)
+            .ok_or_else(|| Error::NotFound {
+                resource: format!("open PR for {head_owner}:{branch}"),
+                credential: None,
+            })
+    }
+
     async fn fetch_pr_info(
         &self,
         owner: &str,
         repo: &str,
         pr_number: u64,
         token: &str,
-    ) -> eyre::Result<GetPullRequestResponse> {
+    ) -> error::Result<GetPullRequestResponse> {
         tracing::debug!("fetching pr info");
         // requires `contents:read`
         self.client
@@ -179,7 +502,6 @@ impl Fetcher {
                 None::<()>,
             )
             .await
-            .wrap_err("fetching branch info")
     }
 
     async fn fetch_workflow_runs(
@@ -187,35 +509,142 @@ impl Fetcher {
         owner: &str,
         repo: &str,
         workflow_id: u64,
-        branch_name: impl Into<String>,
+        head_sha: &str,
         token: &str,
-    ) -> eyre::Result<GetWorkflowRunsResponse> {
-        tracing::debug!("fetching workflow runs");
+    ) -> error::Result<Vec<WorkflowRun>> {
+        tracing::debug!(%head_sha, "fetching workflow runs");
         // requires `actions:read`
         self.client
-            .get(
+            .get_paginated::<GetWorkflowRunsResponse, _>(
                 format!(
                     "/repos/{}/{}/actions/workflows/{}/runs",
                     owner, repo, workflow_id,
                 ),
                 token,
                 Some(GetWorkflowRunsQueryArgs {
-                    branch: branch_name.into(),
+                    head_sha: Some(head_sha.to_string()),
+                    ..Default::default()
+                }),
+            )
+            .await
+    }
+
+    /// Fetch only the first page of runs for a branch, the full history can be very long
+    /// Whether any workflow, or the given one, has run for the PR's branch before
+    async fn has_previous_runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        workflow_id: Option<u64>,
+        pr_info: &GetPullRequestResponse,
+        token: &str,
+    ) -> error::Result<bool> {
+        let GetWorkflowRunsResponse {
+            workflow_runs: branch_runs,
+        } = self
+            .fetch_recent_branch_runs(owner, repo, workflow_id, &pr_info.head.branch, token)
+            .await?;
+        // a branch of the same name in another repository is a different PR
+        let head_repo_id = pr_info.head.repo.as_ref().map(|r| r.id);
+        Ok(branch_runs
+            .iter()
+            .any(|run| run.head_repository.as_ref().map(|r| r.id) == head_repo_id))
+    }
+
+    async fn fetch_recent_branch_runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        workflow_id: Option<u64>,
+        branch: &str,
+        token: &str,
+    ) -> error::Result<GetWorkflowRunsResponse> {
+        tracing::debug!(%branch, "fetching recent workflow runs for branch");
+        let path = match workflow_id {
+            Some(workflow_id) => format!(
+                "/repos/{}/{}/actions/workflows/{}/runs",
+                owner, repo, workflow_id,
+            ),
+            None => format!("/repos/{}/{}/actions/runs", owner, repo),
+        };
+        // requires `actions:read`
+        self.client
+            .get(
+                path,
+                token,
+                Some(GetWorkflowRunsQueryArgs {
+                    branch: Some(branch.to_string()),
+                    ..Default::default()
                 }),
             )
             .await
     }
 
+    async fn fetch_runs_for_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        head_sha: &str,
+        token: &str,
+    ) -> error::Result<Vec<WorkflowRun>> {
+        tracing::debug!(%head_sha, "fetching workflow runs for commit");
+        // requires `actions:read`
+        self.client
+            .get_paginated::<GetWorkflowRunsResponse, _>(
+                format!("/repos/{}/{}/actions/runs", owner, repo),
+                token,
+                Some(GetWorkflowRunsQueryArgs {
+                    head_sha: Some(head_sha.to_string()),
+                    ..Default::default()
+                }),
+            )
+            .await
+    }
+
+    /// Typical durations of a workflow's jobs and steps, from its most recent successful runs
+    ///
+    /// For estimating progress before any run of the workflow has been seen locally.
+    pub async fn past_durations(
+        &self,
+        owner: &str,
+        repo: &str,
+        workflow_id: u64,
+        token: &str,
+    ) -> error::Result<(JobDurations, StepDurations)> {
+        tracing::debug!(%workflow_id, "fetching recent successful runs");
+        // requires `actions:read`
+        let GetWorkflowRunsResponse { workflow_runs } = self
+            .client
+            .get(
+                format!("/repos/{owner}/{repo}/actions/workflows/{workflow_id}/runs"),
+                token,
+                Some(GetWorkflowRunsQueryArgs {
+                    status: Some("success".to_string()),
+                    per_page: Some(PAST_RUNS as u32),
+                    ..Default::default()
+                }),
+            )
+            .await?;
+        let mut jobs = Vec::new();
+        for run in workflow_runs.iter().take(PAST_RUNS) {
+            jobs.extend(self.fetch_run_jobs(owner, repo, run.id, token).await?);
+        }
+        Ok((
+            estimate::job_durations(&jobs),
+            estimate::step_durations(&jobs),
+        ))
+    }
+
     async fn fetch_run_jobs(
         &self,
         owner: &str,
         repo: &str,
         run_id: u64,
         token: &str,
-    ) -> eyre::Result<GetRunJobsResponse> {
+    ) -> error::Result<Vec<RunJob>> {
         // requires `actions:read`
         self.client
-            .get(
+            .get_paginated::<GetRunJobsResponse, _>(
                 format!("/repos/{}/{}/actions/runs/{}/jobs", owner, repo, run_id,),
                 token,
                 None::<()>,
@@ -224,6 +653,27 @@ impl Fetcher {
     }
 }
 
+/// A PR found by [`Fetcher::search_my_prs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPr {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+/// The PR a [`GitHubReference`] points at
+#[derive(Debug, Clone, Serialize)]
+pub struct ReferencedPr {
+    pub owner: String,
+    pub repo: String,
+    pub pr_number: u64,
+    pub title: String,
+    /// The workflow to watch, `None` to watch all of them
+    pub workflow_id: Option<u64>,
+    /// Workflows that ran against the head of the PR
+    pub workflows: Vec<WorkflowDetails>,
+}
+
 #[derive(Debug)]
 struct ProgressResult {
     progress: f32,
@@ -237,7 +687,7 @@ fn calculate_progress(jobs: &[RunJob]) -> ProgressResult {
     for job in jobs {
         let n_steps = u64::try_from(job.steps.len()).unwrap();
 
-        if job.status == "completed" {
+        if job.status == RunStatus::Completed {
             n_steps_total += n_steps;
             completed_steps += n_steps;
             continue;
@@ -245,51 +695,731 @@ fn calculate_progress(jobs: &[RunJob]) -> ProgressResult {
 
         for step in &job.steps {
             n_steps_total += 1;
-            if step.status == "completed" {
+            if step.status == RunStatus::Completed {
                 completed_steps += 1;
             }
         }
     }
     tracing::trace!(%completed_steps, %n_steps_total, "calculated progress percentage");
 
-    // TODO: fallable cast
-
     ProgressResult {
-        progress: (completed_steps as f32) / (n_steps_total as f32),
+        progress: step_fraction(completed_steps, n_steps_total),
         complete: completed_steps,
         total: n_steps_total,
     }
 }
 
-#[derive(Debug, Serialize, Clone, Copy)]
+/// Fraction of steps complete, 0 before any steps exist, e.g. while a run is queued
+fn step_fraction(complete: u64, total: u64) -> f32 {
+    if total == 0 {
+        return 0.0;
+    }
+    complete as f32 / total as f32
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum Status {
     Queued,
-    #[allow(dead_code)]
+    /// Waiting on an external event, e.g. a deployment review
+    Waiting,
     InProgress(f32),
     Succeeded,
     Failed,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    Neutral,
+    Stale,
+    StartupFailure,
+    Unknown,
 }
 
-#[derive(Debug, Serialize, Clone)]
+impl Status {
+    fn from_run(status: RunStatus, conclusion: Option<RunConclusion>, progress: f32) -> Self {
+        match status {
+            RunStatus::Requested | RunStatus::Pending | RunStatus::Queued => Status::Queued,
+            RunStatus::Waiting => Status::Waiting,
+            RunStatus::InProgress => Status::InProgress(progress),
+            RunStatus::Completed => match conclusion {
+                Some(RunConclusion::Success) => Status::Succeeded,
+                Some(RunConclusion::Failure) => Status::Failed,
+                Some(RunConclusion::Cancelled) => Status::Cancelled,
+                Some(RunConclusion::Skipped) => Status::Skipped,
+                Some(RunConclusion::TimedOut) => Status::TimedOut,
+                Some(RunConclusion::ActionRequired) => Status::ActionRequired,
+                Some(RunConclusion::Neutral) => Status::Neutral,
+                Some(RunConclusion::Stale) => Status::Stale,
+                Some(RunConclusion::StartupFailure) => Status::StartupFailure,
+                Some(RunConclusion::Unknown) | None => {
+                    tracing::warn!(?conclusion, "unhandled conclusion for completed run");
+                    Status::Unknown
+                }
+            },
+            RunStatus::Unknown => {
+                tracing::warn!("unhandled run status");
+                Status::Unknown
+            }
+        }
+    }
+
+    fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Status::Failed | Status::TimedOut | Status::StartupFailure
+        )
+    }
+
+    /// Whether the run has yet to finish
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            Status::Queued | Status::Waiting | Status::InProgress(_)
+        )
+    }
+
+    /// Combine the statuses of several workflows into one overall status
+    ///
+    /// Failures take precedence over everything else, then any workflow that is still running,
+    /// then outcomes that need attention. The combined status is only `Succeeded` if every
+    /// workflow finished without a problem.
+    fn combine(statuses: &[Status], progress: f32) -> Status {
+        if let Some(failure) = statuses.iter().find(|s| s.is_failure()) {
+            return *failure;
+        }
+
+        let any = |target: Status| statuses.contains(&target);
+        if statuses.iter().any(|s| matches!(s, Status::InProgress(_))) {
+            Status::InProgress(progress)
+        } else if any(Status::Queued) {
+            Status::Queued
+        } else if any(Status::Waiting) {
+            Status::Waiting
+        } else if any(Status::ActionRequired) {
+            Status::ActionRequired
+        } else if any(Status::Cancelled) {
+            Status::Cancelled
+        } else if any(Status::Stale) {
+            Status::Stale
+        } else if any(Status::Unknown) {
+            Status::Unknown
+        } else if !statuses.is_empty() && statuses.iter().all(|s| *s == Status::Skipped) {
+            Status::Skipped
+        } else {
+            Status::Succeeded
+        }
+    }
+}
+
+/// Status of a single workflow run
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WorkflowStatus {
+    pub workflow_id: u64,
+    pub run_id: u64,
+    /// Re-runs keep the run's id and increment this instead
+    #[serde(default = "first_attempt")]
+    pub run_attempt: u64,
+    pub name: String,
+    pub status: Status,
+    pub num_steps: u64,
+    pub num_complete_steps: u64,
+    pub run_url: String,
+    pub jobs: Vec<RunJob>,
+    #[serde(default)]
+    pub timing: Timing,
+}
+
+fn first_attempt() -> u64 {
+    1
+}
+
+/// Whether a PR is still open, as opposed to the status of its CI
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrState {
+    Open,
+    Merged,
+    Closed,
+}
+
+impl PrState {
+    /// The state of a PR, and the unix timestamp it was merged or closed at
+    fn from_response(pr: &GetPullRequestResponse) -> (Self, Option<i64>) {
+        match pr.state {
+            PullRequestState::Open => (PrState::Open, None),
+            PullRequestState::Closed if pr.merged => (
+                PrState::Merged,
+                pr.merged_at.or(pr.closed_at).map(|at| at.timestamp()),
+            ),
+            PullRequestState::Closed => (PrState::Closed, pr.closed_at.map(|at| at.timestamp())),
+        }
+    }
+}
+
+/// When a run was queued, started and completed, and how long it waited for a runner
+///
+/// How long a run has been going for is left to whoever shows it, so that a run's status does
+/// not change on every poll just because time has passed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct Timing {
+    pub created_at: Option<DateTime<Utc>>,
+    /// `None` until a runner has picked up the first job
+    pub started_at: Option<DateTime<Utc>>,
+    /// `None` until everything has completed
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Seconds spent waiting for a runner
+    pub queued_secs: Option<i64>,
+}
+
+impl Timing {
+    fn new(
+        created_at: Option<DateTime<Utc>>,
+        started_at: Option<DateTime<Utc>>,
+        completed_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            created_at,
+            started_at,
+            completed_at,
+            queued_secs: created_at
+                .zip(started_at)
+                .map(|(created, started)| (started - created).num_seconds().max(0)),
+        }
+    }
+
+    /// Timing of the latest attempt of a run, from the jobs of that attempt
+    ///
+    /// The run itself keeps the time it was first created at when re-run, so its jobs are
+    /// the only record of when the latest attempt was queued.
+    fn of_run(run: &WorkflowRun, jobs: &[RunJob]) -> Self {
+        // older GitHub Enterprise Server versions do not report when jobs were queued
+        let created_at = jobs
+            .iter()
+            .filter_map(|job| job.created_at)
+            .min()
+            .or((run.run_attempt == 1).then_some(run.created_at));
+        // jobs still waiting for a runner already report a start time
+        let started_at = jobs
+            .iter()
+            .filter(|job| {
+                !matches!(
+                    job.status,
+                    RunStatus::Requested
+                        | RunStatus::Pending
+                        | RunStatus::Queued
+                        | RunStatus::Waiting
+                )
+            })
+            .map(|job| job.started_at)
+            .min();
+        // runs only report when they were last updated, so they end with their last job
+        let completed_at = (run.status == RunStatus::Completed).then(|| {
+            jobs.iter()
+                .filter_map(|job| job.completed_at)
+                .max()
+                .unwrap_or(run.run_started_at)
+        });
+        Self::new(created_at, started_at, completed_at)
+    }
+
+    /// From the first of several runs being queued until the last one completes
+    fn combine(timings: &[Timing]) -> Self {
+        let completed_at = timings
+            .iter()
+            .map(|t| t.completed_at)
+            .collect::<Option<Vec<_>>>()
+            .and_then(|times| times.into_iter().max());
+        Self::new(
+            timings.iter().filter_map(|t| t.created_at).min(),
+            timings.iter().filter_map(|t| t.started_at).min(),
+            completed_at,
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Pr {
     pub status: Status,
+    pub state: PrState,
+    /// Unix timestamp (in seconds) the PR was merged or closed at
+    pub closed_at: Option<i64>,
     pub title: String,
+    /// Name of the PR's head branch
+    pub branch: String,
     pub description: String,
     pub num_steps: u64,
     pub num_complete_steps: u64,
     pub pr_url: String,
     pub run_url: String,
+    /// Per-workflow breakdown of the overall status
+    pub workflows: Vec<WorkflowStatus>,
+    /// Progress weighted by how long each job took in past runs, `None` without any history
+    pub estimated_progress: Option<f32>,
+    /// Unix timestamp (in seconds) the PR's runs are expected to complete at
+    pub estimated_completion: Option<i64>,
+    /// Of all of the PR's runs together
+    #[serde(default)]
+    pub timing: Timing,
+}
+
+impl Pr {
+    fn without_runs(pr_info: GetPullRequestResponse) -> Self {
+        let (state, closed_at) = PrState::from_response(&pr_info);
+        Self {
+            status: Status::Unknown,
+            state,
+            closed_at,
+            title: pr_info.title,
+            branch: pr_info.head.branch,
+            description: pr_info.description.unwrap_or_default(),
+            num_steps: 0,
+            num_complete_steps: 0,
+            run_url: format!("{}/checks", pr_info.url),
+            pr_url: pr_info.url,
+            workflows: Vec::new(),
+            estimated_progress: None,
+            estimated_completion: None,
+            timing: Timing::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl WorkflowStatus {
+    /// A run of the "ci" workflow, shared by tests that only care about a few fields
+    pub fn example(run_id: u64, status: Status, jobs: Vec<RunJob>) -> Self {
+        Self {
+            workflow_id: 10,
+            run_id,
+            run_attempt: 1,
+            name: "ci".into(),
+            status,
+            num_steps: 0,
+            num_complete_steps: 0,
+            run_url: String::new(),
+            jobs,
+            timing: Timing::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Pr {
+    /// An open PR made up of `workflows`, shared by tests that only care about a few fields
+    pub fn example(status: Status, workflows: Vec<WorkflowStatus>) -> Self {
+        Self {
+            status,
+            state: PrState::Open,
+            closed_at: None,
+            title: "My PR".into(),
+            branch: "my-branch".into(),
+            description: String::new(),
+            num_steps: 0,
+            num_complete_steps: 0,
+            pr_url: String::new(),
+            run_url: String::new(),
+            workflows,
+            estimated_progress: None,
+            estimated_completion: None,
+            timing: Timing::default(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use approx::assert_abs_diff_eq;
+    use httpmock::prelude::*;
+    use serde_json::json;
 
     use crate::{
-        fetcher::{calculate_progress, ProgressResult},
-        github::GetRunJobsResponse,
+        config::{AppConfig, HostConfig},
+        error::Error,
+        fetcher::{calculate_progress, Fetcher, Fetchers, ProgressResult, Status, Timing},
+        github::{GetRunJobsResponse, RunConclusion, RunStatus, WorkflowRun},
+        reference::GitHubReference,
     };
 
+    #[test]
+    fn status_from_completed_run() {
+        let cases = [
+            (Some(RunConclusion::Success), Status::Succeeded),
+            (Some(RunConclusion::Failure), Status::Failed),
+            (Some(RunConclusion::Cancelled), Status::Cancelled),
+            (Some(RunConclusion::TimedOut), Status::TimedOut),
+            (Some(RunConclusion::StartupFailure), Status::StartupFailure),
+            (None, Status::Unknown),
+        ];
+        for (conclusion, expected) in cases {
+            assert_eq!(
+                Status::from_run(RunStatus::Completed, conclusion, 1.0),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn combine_workflow_statuses() {
+        assert_eq!(
+            Status::combine(&[Status::Succeeded, Status::InProgress(0.2)], 0.6),
+            Status::InProgress(0.6)
+        );
+        assert_eq!(
+            Status::combine(&[Status::InProgress(0.2), Status::TimedOut], 0.6),
+            Status::TimedOut
+        );
+        assert_eq!(
+            Status::combine(&[Status::Succeeded, Status::Skipped], 1.0),
+            Status::Succeeded
+        );
+        assert_eq!(
+            Status::combine(&[Status::Succeeded, Status::Cancelled], 1.0),
+            Status::Cancelled
+        );
+    }
+
+    #[test]
+    fn combine_workflow_timings() {
+        let at = |time: &str| Some(time.parse().unwrap());
+        let done = Timing::new(
+            at("2024-11-20T09:58:00Z"),
+            at("2024-11-20T10:00:00Z"),
+            at("2024-11-20T10:03:00Z"),
+        );
+        assert_eq!(done.queued_secs, Some(120));
+        let running = Timing::new(at("2024-11-20T09:59:00Z"), at("2024-11-20T10:01:00Z"), None);
+
+        let timing = Timing::combine(&[done, running]);
+        assert_eq!(timing.queued_secs, Some(120));
+        // still running for as long as the earliest run has been
+        assert_eq!(timing.started_at, done.started_at);
+        assert_eq!(timing.completed_at, None);
+    }
+
+    #[test]
+    fn rerun_is_timed_from_its_jobs() {
+        let run: WorkflowRun = serde_json::from_value(json!({
+            "id": 11958835219u64,
+            "workflow_id": 10,
+            "name": "CI",
+            "head_sha": "34fb3300b9a77bebdc988ec3edd0d4a6a42a26f9",
+            "head_branch": "estimate-progress",
+            "head_repository": { "id": 1, "full_name": "owner/repo" },
+            "status": "in_progress",
+            "conclusion": null,
+            "display_title": "Estimate progress",
+            "run_attempt": 2,
+            "run_number": 42,
+            // the first attempt, the day before
+            "created_at": "2024-11-19T16:40:02Z",
+            "run_started_at": "2024-11-20T10:14:31Z",
+            "head_commit": {
+                "id": "34fb3300b9a77bebdc988ec3edd0d4a6a42a26f9",
+                "message": "",
+                "timestamp": "2024-11-19T16:39:51Z",
+            },
+            "html_url": "https://github.com/owner/repo/actions/runs/11958835219",
+        }))
+        .unwrap();
+        let s = std::fs::read_to_string("testdata/in-progress-jobs.json").unwrap();
+        let GetRunJobsResponse { mut jobs } = serde_json::from_str(&s).unwrap();
+
+        let timing = Timing::of_run(&run, &jobs);
+        assert_eq!(
+            timing.created_at,
+            Some("2024-11-20T10:14:31Z".parse().unwrap())
+        );
+        assert_eq!(
+            timing.started_at,
+            Some("2024-11-20T10:14:34Z".parse().unwrap())
+        );
+        assert_eq!(timing.queued_secs, Some(3));
+        assert_eq!(timing.completed_at, None);
+
+        // a job still waiting for a runner has not started, whatever it reports
+        for job in &mut jobs {
+            job.status = RunStatus::Queued;
+        }
+        let timing = Timing::of_run(&run, &jobs);
+        assert_eq!(timing.started_at, None);
+        assert_eq!(timing.queued_secs, None);
+    }
+
+    #[test]
+    fn skips_hosts_that_fail_to_set_up() {
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                name: "github.example.com".into(),
+                api_base_url: "https://github.example.com/api/v3".into(),
+                web_base_url: "https://github.example.com".into(),
+                ca_bundle: Some("/does/not/exist.pem".into()),
+                oauth_client_id: None,
+            }],
+            ..Default::default()
+        };
+        let fetchers = Fetchers::from_config(Arc::new(config));
+        assert!(fetchers.get("github.com").is_ok());
+        assert_eq!(
+            fetchers.get("github.example.com").err(),
+            Some(Error::UnknownHost {
+                host: "github.example.com".into()
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_run_status_deserializes() {
+        let status: RunStatus = serde_json::from_str(r#""some_new_status""#).unwrap();
+        assert_eq!(status, RunStatus::Unknown);
+        let conclusion: RunConclusion = serde_json::from_str(r#""timed_out""#).unwrap();
+        assert_eq!(conclusion, RunConclusion::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn no_run_for_latest_commit() {
+        let server = MockServer::start_async().await;
+        let pr_mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(200).json_body(json!({
+                    "title": "My PR",
+                    "body": null,
+                    "html_url": "https://github.com/owner/repo/pull/1",
+                    "head": {
+                        "ref": "main",
+                        "sha": "new-sha",
+                        "repo": { "id": 2, "full_name": "fork/repo", "fork": true },
+                    },
+                }));
+            })
+            .await;
+        let run = |id: u64, sha: &str, repo_id: u64| {
+            json!({
+                "id": id,
+                "workflow_id": 10,
+                "name": "ci",
+                "head_sha": sha,
+                "head_branch": "main",
+                "head_repository": { "id": repo_id, "full_name": "some/repo" },
+                "status": "completed",
+                "conclusion": "success",
+                "display_title": "My PR",
+                "run_attempt": 1,
+                "run_number": id,
+                "created_at": "2024-01-01T00:00:00Z",
+                "run_started_at": "2024-01-01T00:00:00Z",
+                "head_commit": { "id": sha, "message": "", "timestamp": "2024-01-01T00:00:00Z" },
+                "html_url": "https://github.com/owner/repo/actions/runs/1",
+            })
+        };
+        let sha_runs_mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/workflows/10/runs")
+                    .query_param("head_sha", "new-sha");
+                // a run from the upstream repository on a branch with the same name
+                then.status(200)
+                    .json_body(json!({ "workflow_runs": [run(1, "new-sha", 1)] }));
+            })
+            .await;
+        let branch_runs_mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/workflows/10/runs")
+                    .query_param("branch", "main");
+                then.status(200)
+                    .json_body(json!({ "workflow_runs": [run(2, "old-sha", 2)] }));
+            })
+            .await;
+
+        let fetcher = Fetcher::new(server.base_url(), Default::default());
+        let err = fetcher
+            .fetch("abc123", "owner", "repo", 10, 1)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::NoRuns {
+                head_sha: "new-sha".into(),
+                previous_runs: true,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "no workflow run yet for latest commit new-sha"
+        );
+
+        pr_mock.assert_async().await;
+        sha_runs_mock.assert_async().await;
+        branch_runs_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn no_runs_yet_for_latest_push_to_open_pr() {
+        let server = MockServer::start_async().await;
+        let pr_mock = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(200).json_body(json!({
+                    "title": "My PR",
+                    "body": null,
+                    "html_url": "https://github.com/owner/repo/pull/1",
+                    "head": {
+                        "ref": "feature",
+                        "sha": "new-sha",
+                        "repo": { "id": 1, "full_name": "owner/repo", "fork": false },
+                    },
+                }));
+            })
+            .await;
+        let sha_runs_mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/runs")
+                    .query_param("head_sha", "new-sha");
+                then.status(200).json_body(json!({ "workflow_runs": [] }));
+            })
+            .await;
+        let branch_runs_mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/runs")
+                    .query_param("branch", "feature");
+                then.status(200).json_body(json!({
+                    "workflow_runs": [{
+                        "id": 1,
+                        "workflow_id": 10,
+                        "name": "ci",
+                        "head_sha": "old-sha",
+                        "head_branch": "feature",
+                        "head_repository": { "id": 1, "full_name": "owner/repo" },
+                        "status": "completed",
+                        "conclusion": "success",
+                        "display_title": "My PR",
+                        "run_attempt": 1,
+                        "run_number": 1,
+                        "created_at": "2024-01-01T00:00:00Z",
+                        "run_started_at": "2024-01-01T00:00:00Z",
+                        "head_commit": { "id": "old-sha", "message": "", "timestamp": "2024-01-01T00:00:00Z" },
+                        "html_url": "https://github.com/owner/repo/actions/runs/1",
+                    }],
+                }));
+            })
+            .await;
+
+        let fetcher = Fetcher::new(server.base_url(), Default::default());
+        let err = fetcher
+            .fetch_pr_status("abc123", "owner", "repo", 1)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::NoRuns {
+                head_sha: "new-sha".into(),
+                previous_runs: true,
+            }
+        );
+        assert!(err.is_transient());
+
+        pr_mock.assert_async().await;
+        sha_runs_mock.assert_async().await;
+        branch_runs_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn resolves_run_from_fork_to_pr() {
+        let server = MockServer::start_async().await;
+        let run = json!({
+            "id": 5,
+            "workflow_id": 10,
+            "name": "ci",
+            "head_sha": "head-sha",
+            "head_branch": "feature",
+            "head_repository": { "id": 2, "full_name": "fork/repo", "fork": true },
+            "status": "in_progress",
+            "conclusion": null,
+            "display_title": "My PR",
+            "run_attempt": 1,
+            "run_number": 5,
+            "created_at": "2024-01-01T00:00:00Z",
+            "run_started_at": "2024-01-01T00:00:00Z",
+            "head_commit": { "id": "head-sha", "message": "", "timestamp": "2024-01-01T00:00:00Z" },
+            "html_url": "https://github.com/owner/repo/actions/runs/5",
+            "pull_requests": [],
+        });
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/actions/runs/5");
+                then.status(200).json_body(run.clone());
+            })
+            .await;
+        let branch_mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/pulls")
+                    .query_param("head", "fork:feature")
+                    .query_param("state", "open");
+                then.status(200).json_body(json!([{ "number": 7 }]));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/7");
+                then.status(200).json_body(json!({
+                    "title": "My PR",
+                    "body": null,
+                    "html_url": "https://github.com/owner/repo/pull/7",
+                    "head": {
+                        "ref": "feature",
+                        "sha": "head-sha",
+                        "repo": { "id": 2, "full_name": "fork/repo", "fork": true },
+                    },
+                }));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/runs")
+                    .query_param("head_sha", "head-sha");
+                then.status(200)
+                    .json_body(json!({ "workflow_runs": [run.clone()] }));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/actions/workflows");
+                then.status(200).json_body(json!({ "workflows": [
+                    { "id": 10, "name": "ci", "path": ".github/workflows/ci.yml" },
+                    { "id": 11, "name": "release", "path": ".github/workflows/release.yml" },
+                ] }));
+            })
+            .await;
+
+        let fetcher = Fetcher::new(server.base_url(), Default::default());
+        let reference = GitHubReference::Run {
+            host: "github.com".into(),
+            owner: "owner".into(),
+            repo: "repo".into(),
+            run_id: 5,
+        };
+        let pr = fetcher
+            .resolve_reference("abc123", &reference)
+            .await
+            .unwrap();
+
+        assert_eq!(pr.pr_number, 7);
+        assert_eq!(pr.title, "My PR");
+        assert_eq!(pr.workflow_id, Some(10));
+        assert_eq!(
+            pr.workflows.iter().map(|w| w.id).collect::<Vec<_>>(),
+            vec![10]
+        );
+        branch_mock.assert_async().await;
+    }
+
     #[test]
     fn from_example() {
         let s = std::fs::read_to_string("testdata/in-progress-jobs.json").unwrap();
@@ -297,4 +1427,77 @@ mod tests {
         let ProgressResult { progress, .. } = calculate_progress(&jobs);
         assert_abs_diff_eq!(progress, 0.6875, epsilon = 0.001);
     }
+
+    #[test]
+    fn no_progress_before_any_steps() {
+        let ProgressResult {
+            progress, total, ..
+        } = calculate_progress(&[]);
+        assert_eq!(total, 0);
+        assert_eq!(progress, 0.0);
+    }
+
+    #[tokio::test]
+    async fn past_durations_from_recent_successful_runs() {
+        let server = MockServer::start_async().await;
+        let run_json = |id: u64, sha: &str| {
+            json!({
+                "id": id,
+                "workflow_id": 10,
+                "name": "CI",
+                "head_sha": sha,
+                "head_branch": "estimate-progress",
+                "head_repository": { "id": 1, "full_name": "owner/repo" },
+                "status": "completed",
+                "conclusion": "success",
+                "display_title": "Estimate progress",
+                "run_attempt": 1,
+                "run_number": 42,
+                "created_at": "2024-11-18T14:02:11Z",
+                "run_started_at": "2024-11-18T14:02:11Z",
+                "head_commit": { "id": sha, "message": "", "timestamp": "2024-11-18T14:01:58Z" },
+                "html_url": format!("https://github.com/owner/repo/actions/runs/{id}"),
+            })
+        };
+        let runs_mock = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/workflows/10/runs")
+                    .query_param("status", "success")
+                    .query_param("per_page", "3");
+                then.status(200).json_body(json!({ "workflow_runs": [
+                    run_json(11940713462, "second-sha"),
+                    run_json(11921530071, "first-sha"),
+                ] }));
+            })
+            .await;
+        for (run_id, path) in [
+            (11921530071u64, "testdata/completed-jobs.json"),
+            (11940713462, "testdata/completed-jobs-2.json"),
+        ] {
+            let body = std::fs::read_to_string(path).unwrap();
+            server
+                .mock_async(|when, then| {
+                    when.method(GET)
+                        .path(format!("/repos/owner/repo/actions/runs/{run_id}/jobs"));
+                    then.status(200)
+                        .header("content-type", "application/json")
+                        .body(body);
+                })
+                .await;
+        }
+
+        let fetcher = Fetcher::new(server.base_url(), Default::default());
+        let (jobs, steps) = fetcher
+            .past_durations("owner", "repo", 10, "abc123")
+            .await
+            .unwrap();
+        assert_eq!(jobs["lint"], 58);
+        assert_eq!(jobs["test"], 659);
+        assert_eq!(
+            steps[&("test".to_string(), "Run cargo test".to_string())],
+            437
+        );
+        runs_mock.assert_async().await;
+    }
 }
@@ -0,0 +1,304 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::{self, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::config::DEFAULT_HOST;
+
+/// Environment variables the gh CLI reads a github.com token from, in order of precedence
+const GITHUB_TOKEN_VARS: [&str; 2] = ["GH_TOKEN", "GITHUB_TOKEN"];
+
+/// Environment variables the gh CLI reads a GitHub Enterprise Server token from
+const ENTERPRISE_TOKEN_VARS: [&str; 2] = ["GH_ENTERPRISE_TOKEN", "GITHUB_ENTERPRISE_TOKEN"];
+
+/// How long a token read from the gh CLI is reused before it is read again
+const TOKEN_TTL: Duration = Duration::from_secs(60);
+
+/// Where the gh CLI keeps the token for a host
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GhTokenSource {
+    Env {
+        var: String,
+    },
+    /// `oauth_token` in `hosts.yml`
+    HostsFile,
+    /// The system keyring, read with `gh auth token`
+    Keyring,
+}
+
+/// A host the gh CLI is logged in to, without the token itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GhAccount {
+    pub host: String,
+    pub user: Option<String>,
+    pub source: GhTokenSource,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HostEntry {
+    user: Option<String>,
+    oauth_token: Option<String>,
+}
+
+/// Reads the accounts and tokens the gh CLI is logged in with
+///
+/// Reading a token may start `gh` itself, so lookups run on a blocking thread and tokens
+/// are reused for [`TOKEN_TTL`] rather than read for every request.
+pub struct GhCli {
+    config: GhConfig,
+    /// Token last read for each host along with when, `None` if there was none
+    cache: Mutex<HashMap<String, (Instant, Option<String>)>>,
+}
+
+/// Where the gh CLI reads its configuration from, and how it finds tokens
+#[derive(Clone)]
+struct GhConfig {
+    config_dir: Option<PathBuf>,
+    env: fn(&str) -> Option<String>,
+}
+
+impl Default for GhCli {
+    fn default() -> Self {
+        Self::new(default_config_dir(), |name| {
+            std::env::var(name).ok().filter(|v| !v.is_empty())
+        })
+    }
+}
+
+/// The same lookup the gh CLI itself does
+fn default_config_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("GH_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+        return Some(Path::new(&dir).join("gh"));
+    }
+    if cfg!(windows) {
+        if let Ok(dir) = std::env::var("AppData") {
+            return Some(Path::new(&dir).join("GitHub CLI"));
+        }
+    }
+    dirs::home_dir().map(|home| home.join(".config").join("gh"))
+}
+
+impl GhCli {
+    fn new(config_dir: Option<PathBuf>, env: fn(&str) -> Option<String>) -> Self {
+        Self {
+            config: GhConfig { config_dir, env },
+            cache: Default::default(),
+        }
+    }
+
+    /// Every host the gh CLI has a token for
+    pub async fn accounts(&self) -> Vec<GhAccount> {
+        let config = self.config.clone();
+        tokio::task::spawn_blocking(move || config.accounts())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "error reading gh accounts");
+                Vec::new()
+            })
+    }
+
+    /// The token the gh CLI would use for a host
+    ///
+    /// Tokens are read again once they are [`TOKEN_TTL`] old, so tokens rotated by
+    /// `gh auth refresh` are picked up.
+    pub async fn token(&self, host: &str) -> Option<String> {
+        if let Some((read_at, token)) = self.cache.lock().unwrap().get(host) {
+            if read_at.elapsed() < TOKEN_TTL {
+                return token.clone();
+            }
+        }
+
+        let config = self.config.clone();
+        let lookup_host = host.to_string();
+        let token = tokio::task::spawn_blocking(move || config.token(&lookup_host))
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, %host, "error reading gh token");
+                None
+            });
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), (Instant::now(), token.clone()));
+        token
+    }
+}
+
+impl GhConfig {
+    fn accounts(&self) -> Vec<GhAccount> {
+        let hosts = self.hosts().unwrap_or_else(|e| {
+            tracing::debug!(error = ?e, "could not read gh hosts file");
+            BTreeMap::new()
+        });
+
+        let mut accounts: Vec<GhAccount> = hosts
+            .iter()
+            .map(|(host, entry)| GhAccount {
+                host: host.clone(),
+                user: entry.user.clone(),
+                source: self.env_var(host).map_or_else(
+                    || {
+                        if entry.oauth_token.is_some() {
+                            GhTokenSource::HostsFile
+                        } else {
+                            GhTokenSource::Keyring
+                        }
+                    },
+                    |var| GhTokenSource::Env { var },
+                ),
+            })
+            .collect();
+        // a github.com token in the environment works without ever running `gh auth login`
+        if !hosts.contains_key(DEFAULT_HOST) {
+            if let Some(var) = self.env_var(DEFAULT_HOST) {
+                accounts.insert(
+                    0,
+                    GhAccount {
+                        host: DEFAULT_HOST.to_string(),
+                        user: None,
+                        source: GhTokenSource::Env { var },
+                    },
+                );
+            }
+        }
+        accounts
+    }
+
+    fn token(&self, host: &str) -> Option<String> {
+        if let Some(var) = self.env_var(host) {
+            return (self.env)(&var);
+        }
+
+        let entry = self.hosts().ok()?.remove(host)?;
+        match entry.oauth_token {
+            Some(token) => Some(token),
+            None => keyring_token(host),
+        }
+    }
+
+    fn env_var(&self, host: &str) -> Option<String> {
+        let vars = if host == DEFAULT_HOST {
+            GITHUB_TOKEN_VARS
+        } else {
+            ENTERPRISE_TOKEN_VARS
+        };
+        vars.into_iter()
+            .find(|var| (self.env)(var).is_some())
+            .map(str::to_string)
+    }
+
+    fn hosts(&self) -> eyre::Result<BTreeMap<String, HostEntry>> {
+        let Some(dir) = &self.config_dir else {
+            eyre::bail!("no gh config directory");
+        };
+        let path = dir.join("hosts.yml");
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e).wrap_err_with(|| format!("reading {}", path.display())),
+        };
+        // an empty file parses as null
+        let hosts: Option<BTreeMap<String, HostEntry>> =
+            serde_yaml::from_str(&contents).wrap_err("parsing gh hosts file as yaml")?;
+        Ok(hosts.unwrap_or_default())
+    }
+}
+
+/// Recent versions of gh keep tokens in the system keyring rather than `hosts.yml`
+fn keyring_token(host: &str) -> Option<String> {
+    let output = Command::new("gh")
+        .args(["auth", "token", "--hostname", host])
+        .output()
+        .inspect_err(|e| tracing::debug!(error = %e, "could not run gh"))
+        .ok()?;
+    if !output.status.success() {
+        tracing::debug!(%host, status = %output.status, "gh auth token failed");
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!token.is_empty()).then_some(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GhAccount, GhCli, GhTokenSource};
+
+    const HOSTS: &str = r#"
+github.com:
+    users:
+        simonrw:
+            oauth_token: gho_file
+    git_protocol: https
+    oauth_token: gho_file
+    user: simonrw
+github.example.com:
+    git_protocol: ssh
+    user: srw
+"#;
+
+    #[tokio::test]
+    async fn reads_hosts_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hosts.yml"), HOSTS).unwrap();
+        let gh = GhCli::new(Some(dir.path().to_path_buf()), |_| None);
+
+        assert_eq!(
+            gh.accounts().await,
+            vec![
+                GhAccount {
+                    host: "github.com".into(),
+                    user: Some("simonrw".into()),
+                    source: GhTokenSource::HostsFile,
+                },
+                GhAccount {
+                    host: "github.example.com".into(),
+                    user: Some("srw".into()),
+                    source: GhTokenSource::Keyring,
+                },
+            ]
+        );
+        assert_eq!(gh.token("github.com").await.as_deref(), Some("gho_file"));
+
+        // tokens are reused for a while rather than read on every request
+        std::fs::write(
+            dir.path().join("hosts.yml"),
+            HOSTS.replace("gho_file", "gho_new"),
+        )
+        .unwrap();
+        assert_eq!(gh.token("github.com").await.as_deref(), Some("gho_file"));
+    }
+
+    #[tokio::test]
+    async fn environment_takes_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("hosts.yml"), HOSTS).unwrap();
+        let gh = GhCli::new(Some(dir.path().to_path_buf()), |name| {
+            (name == "GITHUB_TOKEN").then(|| "ghp_env".to_string())
+        });
+
+        assert_eq!(
+            gh.accounts().await[0].source,
+            GhTokenSource::Env {
+                var: "GITHUB_TOKEN".into()
+            }
+        );
+        assert_eq!(gh.token("github.com").await.as_deref(), Some("ghp_env"));
+
+        // without a hosts file at all
+        let gh = GhCli::new(None, |name| {
+            (name == "GH_TOKEN").then(|| "ghp_env".to_string())
+        });
+        assert_eq!(gh.accounts().await.len(), 1);
+        assert_eq!(gh.token("github.com").await.as_deref(), Some("ghp_env"));
+    }
+}
@@ -1,29 +1,63 @@
 #![allow(dead_code)]
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{self, Context};
 use reqwest::{
-    header::{HeaderMap, HeaderValue, USER_AGENT},
-    Client,
+    header::{HeaderMap, HeaderValue, ACCEPT, IF_MODIFIED_SINCE, IF_NONE_MATCH, LINK, USER_AGENT},
+    Certificate, Client, Method, Request, RequestBuilder, Response, StatusCode,
 };
 use serde::{Deserialize, Serialize};
 
-use crate::config::AppConfig;
+use crate::{
+    cache::{CachedResponse, ResponseCache},
+    config::{AppConfig, HostConfig},
+    error::{self, Error},
+    rate_limit::{self, RateLimit, RateLimiter, RateLimiters, TokenBudget},
+    retry,
+    token::TokenExpiries,
+};
 
 #[derive(Debug, Deserialize)]
 pub struct Commit {
     pub id: String,
     pub message: String,
-    // TODO: datetime
-    pub timestamp: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// The authenticated user, from `/user`
+#[derive(Debug, Deserialize)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub id: u64,
+    pub full_name: String,
+    #[serde(default)]
+    pub fork: bool,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct Head {
     #[serde(rename = "ref")]
     pub branch: String,
+    pub sha: String,
+    /// The repository the PR branch lives in, `None` if the fork has been deleted
+    pub repo: Option<Repository>,
+}
+
+/// `state` of a PR, merged PRs are `closed` with `merged` set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PullRequestState {
+    #[default]
+    Open,
+    Closed,
 }
+
 #[derive(Debug, Deserialize)]
 pub struct GetPullRequestResponse {
     pub title: String,
@@ -32,6 +66,31 @@ pub struct GetPullRequestResponse {
     pub description: Option<String>,
     #[serde(rename = "html_url")]
     pub url: String,
+    #[serde(default)]
+    pub state: PullRequestState,
+    #[serde(default)]
+    pub merged: bool,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// A PR as listed by `/pulls` or referenced by a workflow run
+#[derive(Debug, Deserialize)]
+pub struct PullRequestSummary {
+    pub number: u64,
+}
+
+/// An issue or PR from the search API
+#[derive(Debug, Deserialize)]
+pub struct SearchIssue {
+    pub number: u64,
+    /// e.g. `https://api.github.com/repos/owner/repo`
+    pub repository_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchIssuesResponse {
+    pub items: Vec<SearchIssue>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,20 +106,61 @@ pub struct GetWorkflowsResponse {
     pub workflows: Vec<WorkflowDetails>,
 }
 
+/// Lifecycle state of a workflow run, job or step
+///
+/// See <https://docs.github.com/en/rest/actions/workflow-runs#list-workflow-runs-for-a-repository>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Requested,
+    Waiting,
+    Pending,
+    Queued,
+    InProgress,
+    Completed,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Outcome of a completed workflow run, job or step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunConclusion {
+    Success,
+    Failure,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    Neutral,
+    Stale,
+    StartupFailure,
+    #[serde(other)]
+    Unknown,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct WorkflowRun {
     pub id: u64,
+    pub workflow_id: u64,
     pub name: String,
-    pub status: String,
-    pub conclusion: Option<String>,
+    pub head_sha: String,
+    pub head_branch: Option<String>,
+    pub head_repository: Option<Repository>,
+    pub status: RunStatus,
+    pub conclusion: Option<RunConclusion>,
     pub display_title: String,
     pub run_attempt: u64,
     pub run_number: u64,
-    // TODO: datetime
-    pub run_started_at: String,
+    pub created_at: DateTime<Utc>,
+    /// Start of the latest attempt of the run
+    pub run_started_at: DateTime<Utc>,
     pub head_commit: Commit,
     #[serde(rename = "html_url")]
     pub url: String,
+    /// PRs in the same repository whose head is the run's commit, empty for PRs from forks
+    #[serde(default)]
+    pub pull_requests: Vec<PullRequestSummary>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,30 +168,67 @@ pub struct GetWorkflowRunsResponse {
     pub workflow_runs: Vec<WorkflowRun>,
 }
 
-#[derive(Serialize)]
+impl WorkflowRun {
+    /// Whether this run was triggered by the current head of the PR
+    ///
+    /// Matching on the branch name alone picks up runs from other forks that share the
+    /// branch name, and runs from before a force-push.
+    pub fn is_for_head(&self, head: &Head) -> bool {
+        if self.head_sha != head.sha {
+            return false;
+        }
+        match (&self.head_repository, &head.repo) {
+            (Some(run_repo), Some(head_repo)) => run_repo.id == head_repo.id,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Serialize, Default)]
 pub struct GetWorkflowRunsQueryArgs {
-    pub branch: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_sha: Option<String>,
+    /// A status or conclusion, e.g. `success`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JobStep {
     pub name: String,
-    pub status: String,
-    pub conclusion: Option<String>,
-    pub started_at: Option<String>,
-    pub completed_at: Option<String>,
+    /// Position of the step in its job, starting from 1
+    pub number: u64,
+    pub status: RunStatus,
+    pub conclusion: Option<RunConclusion>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RunJob {
     pub id: u64,
     pub name: String,
-    pub status: String,
-    pub conclusion: Option<String>,
-    pub started_at: String,
-    pub completed_at: Option<String>,
+    pub status: RunStatus,
+    pub conclusion: Option<RunConclusion>,
+    /// When the job was queued, missing from older GitHub Enterprise Server versions
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
     pub steps: Vec<JobStep>,
 }
+
+impl RunJob {
+    /// How long the job took, `None` until it completes
+    pub fn duration(&self) -> Option<chrono::Duration> {
+        Some(self.completed_at? - self.started_at)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GetRunJobsResponse {
     pub jobs: Vec<RunJob>,
@@ -104,32 +241,120 @@ pub struct GitHubClient {
     client: Client,
     // TODO: borrow
     base_url: String,
+    /// Base URL of the web interface, which serves the OAuth endpoints
+    web_base_url: String,
+    /// Client ID of the OAuth app on this host, if logging in with the device flow is set up
+    oauth_client_id: Option<String>,
     app_config: Arc<AppConfig>,
+    rate_limiters: Arc<RateLimiters>,
+    token_expiries: Arc<TokenExpiries>,
+    cache: Arc<ResponseCache>,
+}
+
+/// Headers and body of a successful response
+struct Page {
+    headers: HeaderMap,
+    body: Vec<u8>,
 }
 
 // Constructors
 impl GitHubClient {
     pub fn new(base_url: impl Into<String>, app_config: Arc<AppConfig>) -> Self {
+        let base_url = base_url.into();
+        let host = HostConfig {
+            api_base_url: base_url.clone(),
+            web_base_url: base_url,
+            ..HostConfig::github()
+        };
+        Self::with_certificate(&host, app_config, None)
+    }
+
+    /// Create a client for a configured host, trusting its custom CA bundle if it has one
+    pub fn for_host(host: &HostConfig, app_config: Arc<AppConfig>) -> eyre::Result<Self> {
+        let certificate = match &host.ca_bundle {
+            Some(path) => {
+                let pem = std::fs::read(path).wrap_err_with(|| {
+                    format!(
+                        "reading CA bundle for {} from {}",
+                        host.name,
+                        path.display()
+                    )
+                })?;
+                let certificate =
+                    Certificate::from_pem(&pem).wrap_err("parsing CA bundle as PEM")?;
+                Some(certificate)
+            }
+            None => None,
+        };
+        Ok(Self::with_certificate(host, app_config, certificate))
+    }
+
+    fn with_certificate(
+        host: &HostConfig,
+        app_config: Arc<AppConfig>,
+        certificate: Option<Certificate>,
+    ) -> Self {
         let mut headers = HeaderMap::new();
         headers.append(USER_AGENT, HeaderValue::from_static("gh-ci-watch"));
 
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .expect("programming error");
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(certificate) = certificate {
+            builder = builder.add_root_certificate(certificate);
+        }
+        let client = builder.build().expect("programming error");
         Self {
             client,
-            base_url: base_url.into(),
+            base_url: host.api_base_url.clone(),
+            web_base_url: host.web_base_url.clone(),
+            oauth_client_id: host.oauth_client_id.clone(),
             app_config,
+            rate_limiters: Default::default(),
+            token_expiries: Default::default(),
+            cache: Default::default(),
         }
     }
 
+    /// The most recently reported budget of the token with the fewest requests left
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limiters.lowest()
+    }
+
+    /// Budget of every token this client has sent requests with
+    pub fn budgets(&self) -> HashMap<u64, TokenBudget> {
+        self.rate_limiters.budgets()
+    }
+
+    /// Unix timestamp (in seconds) a token this client has sent requests with expires at
+    pub fn token_expiry(&self, token: &str) -> Option<i64> {
+        self.token_expiries.expires_at(token)
+    }
+
+    /// Total number of requests sent by this client
+    pub fn request_count(&self) -> u64 {
+        self.budgets().values().map(|b| b.requests).sum()
+    }
+
     pub async fn get<T, Q>(
         &self,
         path: impl Into<String>,
         token: &str,
         query: Option<Q>,
-    ) -> eyre::Result<T>
+    ) -> error::Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+        Q: Serialize,
+    {
+        let (body, _) = self.get_with_headers(path, token, query).await?;
+        Ok(body)
+    }
+
+    /// Like [`GitHubClient::get`], also returning the response headers
+    pub async fn get_with_headers<T, Q>(
+        &self,
+        path: impl Into<String>,
+        token: &str,
+        query: Option<Q>,
+    ) -> error::Result<(T, HeaderMap)>
     where
         T: for<'de> serde::Deserialize<'de>,
         Q: Serialize,
@@ -141,19 +366,739 @@ impl GitHubClient {
             builder = builder.query(query);
         }
 
-        tracing::debug!("sending http request");
-        let response = builder.send().await.wrap_err("sending GET request")?;
-        if let Err(e) = response
-            .error_for_status_ref()
-            .context("bad status response")
-        {
-            tracing::warn!(error = %e, "bad status from GitHub");
-            if self.app_config.enable_sentry {
-                sentry_eyre::capture_report(&e);
+        let Page { headers, body } = self.send(builder, token).await?;
+        let body = serde_json::from_slice(&body).map_err(|e| Error::decode(&e))?;
+        Ok((body, headers))
+    }
+
+    /// Fetch every page of a list endpoint, following the `Link: rel="next"` header
+    ///
+    /// Pages are `per_page` items long as set in the config. At most [`MAX_PAGES`] pages are
+    /// fetched, to stop runaway requests against endpoints with very long histories.
+    pub async fn get_paginated<T, Q>(
+        &self,
+        path: impl Into<String>,
+        token: &str,
+        query: Option<Q>,
+    ) -> error::Result<Vec<T::Item>>
+    where
+        T: Paginated,
+        Q: Serialize,
+    {
+        let path = path.into();
+        let url = format!("{}{}", &self.base_url, &path);
+        let per_page = self.app_config.per_page.clamp(1, MAX_PER_PAGE);
+        let mut builder = self
+            .client
+            .get(url)
+            .bearer_auth(token)
+            .query(&[("per_page", per_page)]);
+        if let Some(query) = &query {
+            builder = builder.query(query);
+        }
+
+        let mut items = Vec::new();
+        for page in 1..=MAX_PAGES {
+            let Page { headers, body } = self.send(builder, token).await?;
+            let next_url = next_page_url(&headers);
+            let body: T = serde_json::from_slice(&body).map_err(|e| Error::decode(&e))?;
+            items.extend(body.into_items());
+
+            let Some(next_url) = next_url else {
+                tracing::trace!(%page, num_items = %items.len(), "fetched last page");
+                return Ok(items);
+            };
+            tracing::trace!(%page, %next_url, "fetching next page");
+            // the next page URL already includes the query parameters
+            builder = self.client.get(next_url).bearer_auth(token);
+        }
+
+        tracing::warn!(%path, max_pages = %MAX_PAGES, "reached page limit, results truncated");
+        Ok(items)
+    }
+
+    /// Send a request, revalidating any cached response for the same URL and token
+    ///
+    /// The request counts against the budget of `token`, the token it is authorized with.
+    async fn send(&self, builder: RequestBuilder, token: &str) -> error::Result<Page> {
+        let rate_limiter = self.rate_limiters.for_token(token);
+        if let Err(until) = rate_limiter.check(rate_limit::unix_now()) {
+            tracing::debug!(%until, "skipping request while rate limited");
+            return Err(Error::RateLimited { until });
+        }
+
+        let mut request = builder.build().map_err(|e| Error::network(&e))?;
+        let path = request.url().path().to_string();
+        // a response for one account must never be replayed to another
+        let cache_key = format!("{:x} {}", rate_limit::token_key(token), request.url());
+        let cached = self.cache.get(&cache_key);
+        if let Some(cached) = &cached {
+            let headers = request.headers_mut();
+            if let Some(etag) = &cached.etag {
+                headers.insert(IF_NONE_MATCH, etag.clone());
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                headers.insert(IF_MODIFIED_SINCE, last_modified.clone());
+            }
+        }
+
+        let response = self
+            .execute_with_retry(request, &rate_limiter)
+            .await
+            .map_err(|e| Error::network(&e))?;
+        self.token_expiries.record(token, response.headers());
+        if let Some(until) = rate_limiter.record(
+            response.status(),
+            response.headers(),
+            rate_limit::unix_now(),
+        ) {
+            return Err(Error::RateLimited { until });
+        }
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                tracing::debug!("response not modified, using cached body");
+                return Ok(Page {
+                    headers: cached.headers,
+                    body: cached.body,
+                });
+            }
+        }
+        if !response.status().is_success() {
+            let error = status_error(response.status(), response.headers(), path);
+            tracing::warn!(%error, "bad status from GitHub");
+            // auth and permission errors are expected, only report the unexpected ones
+            if self.app_config.enable_sentry && matches!(error, Error::Status { .. }) {
+                sentry_eyre::capture_report(&eyre::Report::new(error.clone()));
             }
-            eyre::bail!("bad error status: {e}");
+            return Err(error);
         }
+
         tracing::debug!("got http response");
-        response.json().await.wrap_err("decoding JSON response")
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| Error::network(&e))?
+            .to_vec();
+        if let Some(cached) = CachedResponse::new(&headers, &body) {
+            self.cache.insert(cache_key, cached);
+        }
+        Ok(Page { headers, body })
+    }
+
+    /// Send a request, retrying idempotent requests that fail for transient reasons
+    ///
+    /// The outcome of the final attempt is returned once the retry policy is exhausted.
+    async fn execute_with_retry(
+        &self,
+        request: Request,
+        rate_limiter: &RateLimiter,
+    ) -> reqwest::Result<Response> {
+        let policy = self.app_config.retry;
+        let max_attempts = if request.method() == Method::GET {
+            policy.max_attempts.max(1)
+        } else {
+            1
+        };
+
+        let mut attempt = 1;
+        loop {
+            let Some(attempt_request) = request.try_clone().filter(|_| attempt < max_attempts)
+            else {
+                tracing::debug!(%attempt, "sending http request");
+                return self.client.execute(request).await;
+            };
+
+            tracing::debug!(%attempt, "sending http request");
+            let result = self.client.execute(attempt_request).await;
+            if !retry::is_transient(&result) {
+                return result;
+            }
+
+            let delay = policy.backoff(attempt);
+            match &result {
+                Ok(response) => {
+                    // failed attempts still count against the budget
+                    rate_limiter.record(
+                        response.status(),
+                        response.headers(),
+                        rate_limit::unix_now(),
+                    );
+                    tracing::info!(status = %response.status(), %attempt, ?delay, "retrying server error");
+                }
+                Err(e) => {
+                    tracing::info!(error = %e, %attempt, ?delay, "retrying network error");
+                }
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Scopes requested when logging in with the device flow, `repo` is needed to read the
+/// Actions data of private repositories
+pub const DEVICE_LOGIN_SCOPES: &str = "repo";
+
+/// Codes returned when starting the OAuth device flow
+///
+/// See <https://docs.github.com/en/apps/oauth-apps/building-oauth-apps/authorizing-oauth-apps#device-flow>
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    /// Secret used to poll for the token, never shown to the user
+    pub device_code: String,
+    /// Code the user enters at `verification_uri`
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Seconds until the codes expire
+    pub expires_in: u64,
+    /// Minimum number of seconds between polls
+    pub interval: u64,
+}
+
+/// Outcome of polling for the token during the device flow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DevicePoll {
+    /// The user has not entered the code yet, poll again after `interval` seconds
+    Pending {
+        interval: Option<u64>,
+    },
+    Complete {
+        token: String,
+    },
+}
+
+/// Body of `/login/oauth/access_token`, which reports errors with a `200` status
+#[derive(Debug, Deserialize)]
+struct AccessTokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+    interval: Option<u64>,
+}
+
+// OAuth device flow
+impl GitHubClient {
+    /// Ask GitHub for a code the user can enter to authorize the app
+    pub async fn start_device_flow(&self) -> error::Result<DeviceCode> {
+        let client_id = self.oauth_client_id()?;
+        let body: DeviceCode = self
+            .post_form(
+                "/login/device/code",
+                &[("client_id", client_id), ("scope", DEVICE_LOGIN_SCOPES)],
+            )
+            .await?;
+        Ok(body)
+    }
+
+    /// Check whether the user has entered the code yet
+    pub async fn poll_device_flow(&self, device_code: &str) -> error::Result<DevicePoll> {
+        let client_id = self.oauth_client_id()?;
+        let body: AccessTokenResponse = self
+            .post_form(
+                "/login/oauth/access_token",
+                &[
+                    ("client_id", client_id),
+                    ("device_code", device_code),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ],
+            )
+            .await?;
+
+        if let Some(token) = body.access_token {
+            return Ok(DevicePoll::Complete { token });
+        }
+        match body.error.as_deref() {
+            Some("authorization_pending") => Ok(DevicePoll::Pending { interval: None }),
+            // GitHub adds 5 seconds to the interval every time we poll too fast
+            Some("slow_down") => Ok(DevicePoll::Pending {
+                interval: body.interval,
+            }),
+            Some("expired_token") => Err(Error::DeviceLoginExpired),
+            Some("access_denied") => Err(Error::AccessDenied),
+            error => Err(Error::OAuth {
+                error: error.unwrap_or("missing_token").to_string(),
+                description: body.error_description,
+            }),
+        }
+    }
+
+    fn oauth_client_id(&self) -> error::Result<&str> {
+        self.oauth_client_id
+            .as_deref()
+            .ok_or(Error::DeviceLoginUnavailable)
+    }
+
+    /// The OAuth endpoints take form encoded bodies, are not rate limited like the API and
+    /// must not be retried as each device code may only be exchanged once
+    async fn post_form<T>(&self, path: &str, form: &[(&str, &str)]) -> error::Result<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let url = format!("{}{}", &self.web_base_url, path);
+        tracing::debug!(%url, "sending oauth request");
+        let response = self
+            .client
+            .post(url)
+            .header(ACCEPT, "application/json")
+            .form(form)
+            .send()
+            .await
+            .map_err(|e| Error::network(&e))?;
+        if !response.status().is_success() {
+            return Err(status_error(
+                response.status(),
+                response.headers(),
+                path.to_string(),
+            ));
+        }
+        let body = response.bytes().await.map_err(|e| Error::network(&e))?;
+        serde_json::from_slice(&body).map_err(|e| Error::decode(&e))
+    }
+}
+
+/// Map an unsuccessful response to the matching error
+fn status_error(status: StatusCode, headers: &HeaderMap, path: String) -> Error {
+    match status {
+        StatusCode::UNAUTHORIZED => Error::Unauthorized { credential: None },
+        StatusCode::FORBIDDEN => {
+            // fine-grained tokens report missing permissions, classic tokens missing scopes
+            let required = ["x-accepted-github-permissions", "x-accepted-oauth-scopes"]
+                .iter()
+                .filter_map(|name| headers.get(*name)?.to_str().ok())
+                .find(|value| !value.is_empty())
+                .map(str::to_string);
+            Error::Forbidden {
+                required,
+                credential: None,
+            }
+        }
+        StatusCode::NOT_FOUND => Error::NotFound {
+            resource: path,
+            credential: None,
+        },
+        status => Error::Status {
+            status: status.as_u16(),
+        },
+    }
+}
+
+/// The most items per page GitHub allows, and the default
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// Maximum number of pages fetched by [`GitHubClient::get_paginated`]
+pub const MAX_PAGES: usize = 10;
+
+/// A list response that is split over several pages
+pub trait Paginated: for<'de> Deserialize<'de> {
+    type Item;
+
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+impl Paginated for GetWorkflowsResponse {
+    type Item = WorkflowDetails;
+
+    fn into_items(self) -> Vec<Self::Item> {
+        self.workflows
+    }
+}
+
+impl Paginated for SearchIssuesResponse {
+    type Item = SearchIssue;
+
+    fn into_items(self) -> Vec<Self::Item> {
+        self.items
+    }
+}
+
+impl Paginated for GetWorkflowRunsResponse {
+    type Item = WorkflowRun;
+
+    fn into_items(self) -> Vec<Self::Item> {
+        self.workflow_runs
+    }
+}
+
+impl Paginated for GetRunJobsResponse {
+    type Item = RunJob;
+
+    fn into_items(self) -> Vec<Self::Item> {
+        self.jobs
+    }
+}
+
+/// Extract the URL of the next page from a `Link` header
+///
+/// The header looks like `<https://api.github.com/...&page=2>; rel="next", <...>; rel="last"`.
+fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get(LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        let is_next = params
+            .split(';')
+            .any(|param| param.trim() == r#"rel="next""#);
+        is_next.then(|| {
+            url.trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use reqwest::header::{HeaderMap, HeaderValue, LINK};
+    use serde_json::json;
+
+    use std::{sync::Arc, time::Duration};
+
+    use crate::{
+        config::{AppConfig, HostConfig},
+        retry::RetryPolicy,
+    };
+
+    use crate::error::Error;
+
+    use super::{next_page_url, DevicePoll, GetWorkflowsResponse, GitHubClient};
+
+    fn retrying_client(base_url: String, base_delay_ms: u64) -> GitHubClient {
+        let config = AppConfig {
+            enable_sentry: false,
+            retry: RetryPolicy {
+                max_attempts: 3,
+                base_delay_ms,
+                max_delay_ms: base_delay_ms,
+            },
+            ..Default::default()
+        };
+        GitHubClient::new(base_url, Arc::new(config))
+    }
+
+    fn oauth_client(base_url: String) -> GitHubClient {
+        let host = HostConfig {
+            api_base_url: base_url.clone(),
+            web_base_url: base_url,
+            oauth_client_id: Some("client-123".into()),
+            ..HostConfig::github()
+        };
+        GitHubClient::for_host(&host, Default::default()).unwrap()
+    }
+
+    #[test]
+    fn parse_link_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://api.github.com/repositories/1/actions/workflows?per_page=100&page=2>; rel="next", <https://api.github.com/repositories/1/actions/workflows?per_page=100&page=3>; rel="last""#,
+            ),
+        );
+        assert_eq!(
+            next_page_url(&headers).as_deref(),
+            Some("https://api.github.com/repositories/1/actions/workflows?per_page=100&page=2")
+        );
+
+        headers.insert(
+            LINK,
+            HeaderValue::from_static(
+                r#"<https://api.github.com/repositories/1/actions/workflows?per_page=100&page=1>; rel="prev""#,
+            ),
+        );
+        assert_eq!(next_page_url(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn revalidates_with_etag() {
+        let server = MockServer::start_async().await;
+        let fresh = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/pulls/1")
+                    .matches(|req| {
+                        !req.headers
+                            .iter()
+                            .flatten()
+                            .any(|(name, _)| name.eq_ignore_ascii_case("if-none-match"))
+                    });
+                then.status(200)
+                    .header("etag", r#""abc""#)
+                    .json_body(json!({ "title": "My PR" }));
+            })
+            .await;
+        let not_modified = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/pulls/1")
+                    .header("if-none-match", r#""abc""#);
+                then.status(304);
+            })
+            .await;
+
+        let client = GitHubClient::new(server.base_url(), Default::default());
+        for _ in 0..2 {
+            let body: serde_json::Value = client
+                .get("/repos/owner/repo/pulls/1", "abc123", None::<()>)
+                .await
+                .unwrap();
+            assert_eq!(body, json!({ "title": "My PR" }));
+        }
+
+        fresh.assert_async().await;
+        not_modified.assert_async().await;
+        // the revalidation does not count against the budget
+        assert_eq!(client.request_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_share_cached_responses_between_tokens() {
+        let server = MockServer::start_async().await;
+        let for_token = |token: &'static str, title: &'static str| {
+            server.mock_async(move |when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/pulls/1")
+                    .header("authorization", format!("Bearer {token}"))
+                    .matches(|req| {
+                        !req.headers
+                            .iter()
+                            .flatten()
+                            .any(|(name, _)| name.eq_ignore_ascii_case("if-none-match"))
+                    });
+                then.status(200)
+                    .header("etag", r#""abc""#)
+                    .json_body(json!({ "title": title }));
+            })
+        };
+        let first = for_token("first", "First PR").await;
+        let second = for_token("second", "Second PR").await;
+
+        let client = GitHubClient::new(server.base_url(), Default::default());
+        for (token, title) in [("first", "First PR"), ("second", "Second PR")] {
+            let body: serde_json::Value = client
+                .get("/repos/owner/repo/pulls/1", token, None::<()>)
+                .await
+                .unwrap();
+            assert_eq!(body, json!({ "title": title }));
+        }
+
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn follows_next_links() {
+        let server = MockServer::start_async().await;
+        let workflow = |id: u64| {
+            json!({
+                "id": id,
+                "name": format!("workflow-{id}"),
+                "path": format!(".github/workflows/{id}.yml"),
+            })
+        };
+        let first_page = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/workflows")
+                    .query_param("per_page", "2")
+                    .matches(|req| {
+                        !req.query_params
+                            .iter()
+                            .flatten()
+                            .any(|(name, _)| name == "page")
+                    });
+                then.status(200)
+                    .header(
+                        "link",
+                        format!(
+                            r#"<{}>; rel="next""#,
+                            server.url("/repos/owner/repo/actions/workflows?per_page=2&page=2")
+                        ),
+                    )
+                    .json_body(json!({ "workflows": [workflow(1), workflow(2)] }));
+            })
+            .await;
+        let second_page = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/repos/owner/repo/actions/workflows")
+                    .query_param("page", "2");
+                then.status(200)
+                    .json_body(json!({ "workflows": [workflow(3)] }));
+            })
+            .await;
+
+        let config = AppConfig {
+            per_page: 2,
+            ..Default::default()
+        };
+        let client = GitHubClient::new(server.base_url(), Arc::new(config));
+        let workflows = client
+            .get_paginated::<GetWorkflowsResponse, ()>(
+                "/repos/owner/repo/actions/workflows",
+                "abc123",
+                None,
+            )
+            .await
+            .unwrap();
+        let ids: Vec<u64> = workflows.iter().map(|w| w.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        first_page.assert_async().await;
+        second_page.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn retries_flaky_responses() {
+        let server = MockServer::start_async().await;
+        let flaky = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(503);
+            })
+            .await;
+
+        let client = retrying_client(server.base_url(), 200);
+        let request =
+            client.get::<serde_json::Value, ()>("/repos/owner/repo/pulls/1", "abc123", None);
+        // GitHub recovers while the client is backing off
+        let recover = async {
+            while flaky.hits_async().await == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            flaky.delete_async().await;
+            server
+                .mock_async(|when, then| {
+                    when.method(GET).path("/repos/owner/repo/pulls/1");
+                    then.status(200).json_body(json!({ "title": "My PR" }));
+                })
+                .await
+        };
+        let (body, recovered) = tokio::join!(request, recover);
+
+        assert_eq!(body.unwrap()["title"], "My PR");
+        recovered.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let server = MockServer::start_async().await;
+        let failing = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(502);
+            })
+            .await;
+
+        let client = retrying_client(server.base_url(), 1);
+        let result = client
+            .get::<serde_json::Value, ()>("/repos/owner/repo/pulls/1", "abc123", None)
+            .await;
+
+        assert!(result.is_err());
+        failing.assert_hits_async(3).await;
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = MockServer::start_async().await;
+        let not_found = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(404);
+            })
+            .await;
+
+        let client = retrying_client(server.base_url(), 1);
+        let result = client
+            .get::<serde_json::Value, ()>("/repos/owner/repo/pulls/1", "abc123", None)
+            .await;
+
+        assert!(result.is_err());
+        not_found.assert_hits_async(1).await;
+    }
+
+    #[tokio::test]
+    async fn device_flow() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/login/device/code")
+                    .header("accept", "application/json")
+                    .x_www_form_urlencoded_tuple("client_id", "client-123")
+                    .x_www_form_urlencoded_tuple("scope", "repo");
+                then.status(200).json_body(json!({
+                    "device_code": "device-abc",
+                    "user_code": "WDJB-MJHT",
+                    "verification_uri": "https://github.com/login/device",
+                    "expires_in": 900,
+                    "interval": 5
+                }));
+            })
+            .await;
+        let pending = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/login/oauth/access_token")
+                    .x_www_form_urlencoded_tuple("device_code", "device-abc");
+                then.status(200)
+                    .json_body(json!({ "error": "slow_down", "interval": 10 }));
+            })
+            .await;
+
+        let client = oauth_client(server.base_url());
+
+        let code = client.start_device_flow().await.unwrap();
+        assert_eq!(code.user_code, "WDJB-MJHT");
+        assert_eq!(code.interval, 5);
+
+        assert_eq!(
+            client.poll_device_flow(&code.device_code).await.unwrap(),
+            DevicePoll::Pending { interval: Some(10) }
+        );
+
+        pending.delete_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/login/oauth/access_token");
+                then.status(200).json_body(json!({
+                    "access_token": "gho_abc",
+                    "token_type": "bearer",
+                    "scope": "repo"
+                }));
+            })
+            .await;
+        assert_eq!(
+            client.poll_device_flow(&code.device_code).await.unwrap(),
+            DevicePoll::Complete {
+                token: "gho_abc".into()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn device_flow_errors() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(POST).path("/login/oauth/access_token");
+                then.status(200)
+                    .json_body(json!({ "error": "expired_token" }));
+            })
+            .await;
+
+        let client = GitHubClient::new(server.base_url(), Default::default());
+        assert_eq!(
+            client.poll_device_flow("device-abc").await,
+            Err(Error::DeviceLoginUnavailable)
+        );
+
+        let client = oauth_client(server.base_url());
+        assert_eq!(
+            client.poll_device_flow("device-abc").await,
+            Err(Error::DeviceLoginExpired)
+        );
     }
 }
@@ -0,0 +1,1036 @@
+use std::{path::Path, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{self, Context};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    estimate::{self, JobDurations, StepDurations},
+    fetcher::{Pr, PrState, Status, Timing, WorkflowStatus},
+    github::RunJob,
+    watcher::{StatusEvent, WatchTarget},
+};
+
+const DATABASE_FILE: &str = "history.sqlite3";
+
+/// Number of past runs job durations are taken from, so estimates follow changes to a workflow
+const DURATION_SAMPLE_RUNS: u64 = 10;
+
+/// Schema changes, applied in order
+///
+/// The number of migrations applied is kept in the `user_version` pragma, so existing entries
+/// must never be edited, only new ones appended.
+const MIGRATIONS: &[&str] = &[
+    r#"
+CREATE TABLE watched_prs (
+    host TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    pr_number INTEGER NOT NULL,
+    -- NULL when every workflow is watched
+    workflow_id INTEGER,
+    discovered INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    archived_at INTEGER,
+    -- JSON of the last status of an archived PR
+    final_status TEXT
+);
+CREATE UNIQUE INDEX watched_prs_target
+    ON watched_prs (host, owner, repo, pr_number, IFNULL(workflow_id, -1));
+
+CREATE TABLE runs (
+    host TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    pr_number INTEGER NOT NULL,
+    workflow_id INTEGER NOT NULL,
+    workflow_name TEXT NOT NULL,
+    status TEXT NOT NULL,
+    -- final status, NULL while the run is still going
+    conclusion TEXT,
+    started_at INTEGER,
+    completed_at INTEGER,
+    first_seen_at INTEGER NOT NULL,
+    last_seen_at INTEGER NOT NULL,
+    PRIMARY KEY (host, id)
+);
+
+CREATE TABLE jobs (
+    host TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    run_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    conclusion TEXT,
+    started_at INTEGER,
+    completed_at INTEGER,
+    duration_secs INTEGER,
+    PRIMARY KEY (host, id)
+);
+CREATE INDEX jobs_run ON jobs (host, run_id);
+
+CREATE TABLE transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    host TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    pr_number INTEGER NOT NULL,
+    workflow_id INTEGER,
+    from_status TEXT,
+    to_status TEXT NOT NULL,
+    at INTEGER NOT NULL
+);
+CREATE INDEX transitions_target ON transitions (host, owner, repo, pr_number);
+"#,
+    r#"
+CREATE TABLE steps (
+    host TEXT NOT NULL,
+    job_id INTEGER NOT NULL,
+    number INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    duration_secs INTEGER NOT NULL,
+    PRIMARY KEY (host, job_id, number)
+);
+"#,
+    r#"
+-- discovered PRs the user removed, which discovery must not add back
+CREATE TABLE dismissed_prs (
+    host TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    repo TEXT NOT NULL,
+    pr_number INTEGER NOT NULL,
+    dismissed_at INTEGER NOT NULL,
+    PRIMARY KEY (host, owner, repo, pr_number)
+);
+"#,
+];
+
+/// A PR restored from the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredPr {
+    pub target: WatchTarget,
+    pub discovered: bool,
+    /// Unix timestamp (in seconds) and final status, if the PR has been archived
+    pub archived: Option<(u64, Pr)>,
+}
+
+/// Final status of an archived PR, as stored in `watched_prs.final_status`
+///
+/// Kept apart from [`Pr`] so that changing it does not make older databases unreadable.
+/// Storing something new means adding a version, and converting older ones when read. Jobs
+/// are left out, as the archive only shows the status of the PR and its workflows.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "version")]
+enum FinalStatus {
+    #[serde(rename = "1")]
+    V1(FinalStatusV1),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FinalStatusV1 {
+    status: String,
+    /// Only set while in progress
+    progress: Option<f32>,
+    state: String,
+    closed_at: Option<i64>,
+    title: String,
+    branch: String,
+    description: String,
+    num_steps: u64,
+    num_complete_steps: u64,
+    pr_url: String,
+    run_url: String,
+    timing: TimingV1,
+    workflows: Vec<FinalWorkflowV1>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FinalWorkflowV1 {
+    workflow_id: u64,
+    run_id: u64,
+    run_attempt: u64,
+    name: String,
+    status: String,
+    progress: Option<f32>,
+    num_steps: u64,
+    num_complete_steps: u64,
+    run_url: String,
+    timing: TimingV1,
+}
+
+/// Unix timestamps (in seconds)
+#[derive(Debug, Serialize, Deserialize)]
+struct TimingV1 {
+    created_at: Option<i64>,
+    started_at: Option<i64>,
+    completed_at: Option<i64>,
+    queued_secs: Option<i64>,
+}
+
+impl FinalStatus {
+    fn new(pr: &Pr) -> Self {
+        let progress = |status: &Status| match status {
+            Status::InProgress(progress) => Some(*progress),
+            _ => None,
+        };
+        FinalStatus::V1(FinalStatusV1 {
+            status: status_name(&pr.status).to_string(),
+            progress: progress(&pr.status),
+            state: state_name(pr.state).to_string(),
+            closed_at: pr.closed_at,
+            title: pr.title.clone(),
+            branch: pr.branch.clone(),
+            description: pr.description.clone(),
+            num_steps: pr.num_steps,
+            num_complete_steps: pr.num_complete_steps,
+            pr_url: pr.pr_url.clone(),
+            run_url: pr.run_url.clone(),
+            timing: TimingV1::new(&pr.timing),
+            workflows: pr
+                .workflows
+                .iter()
+                .map(|workflow| FinalWorkflowV1 {
+                    workflow_id: workflow.workflow_id,
+                    run_id: workflow.run_id,
+                    run_attempt: workflow.run_attempt,
+                    name: workflow.name.clone(),
+                    status: status_name(&workflow.status).to_string(),
+                    progress: progress(&workflow.status),
+                    num_steps: workflow.num_steps,
+                    num_complete_steps: workflow.num_complete_steps,
+                    run_url: workflow.run_url.clone(),
+                    timing: TimingV1::new(&workflow.timing),
+                })
+                .collect(),
+        })
+    }
+
+    fn into_pr(self) -> eyre::Result<Pr> {
+        let FinalStatus::V1(status) = self;
+        let workflows = status
+            .workflows
+            .into_iter()
+            .map(|workflow| {
+                Ok(WorkflowStatus {
+                    workflow_id: workflow.workflow_id,
+                    run_id: workflow.run_id,
+                    run_attempt: workflow.run_attempt,
+                    name: workflow.name,
+                    status: parse_status(&workflow.status, workflow.progress)?,
+                    num_steps: workflow.num_steps,
+                    num_complete_steps: workflow.num_complete_steps,
+                    run_url: workflow.run_url,
+                    jobs: Vec::new(),
+                    timing: workflow.timing.into_timing(),
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+        Ok(Pr {
+            status: parse_status(&status.status, status.progress)?,
+            state: parse_state(&status.state)?,
+            closed_at: status.closed_at,
+            title: status.title,
+            branch: status.branch,
+            description: status.description,
+            num_steps: status.num_steps,
+            num_complete_steps: status.num_complete_steps,
+            pr_url: status.pr_url,
+            run_url: status.run_url,
+            workflows,
+            estimated_progress: None,
+            estimated_completion: None,
+            timing: status.timing.into_timing(),
+        })
+    }
+}
+
+impl TimingV1 {
+    fn new(timing: &Timing) -> Self {
+        Self {
+            created_at: timing.created_at.map(|at| at.timestamp()),
+            started_at: timing.started_at.map(|at| at.timestamp()),
+            completed_at: timing.completed_at.map(|at| at.timestamp()),
+            queued_secs: timing.queued_secs,
+        }
+    }
+
+    fn into_timing(self) -> Timing {
+        let at = |secs: Option<i64>| secs.and_then(|secs| DateTime::<Utc>::from_timestamp(secs, 0));
+        Timing {
+            created_at: at(self.created_at),
+            started_at: at(self.started_at),
+            completed_at: at(self.completed_at),
+            queued_secs: self.queued_secs,
+        }
+    }
+}
+
+/// Watched PRs and every run observed for them, kept in a SQLite database
+pub struct History {
+    conn: Mutex<Connection>,
+}
+
+impl History {
+    pub fn open(dir: &Path) -> eyre::Result<Self> {
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("creating data directory {}", dir.display()))?;
+        let path = dir.join(DATABASE_FILE);
+        let conn = Connection::open(&path)
+            .wrap_err_with(|| format!("opening database {}", path.display()))?;
+        Self::from_connection(conn)
+    }
+
+    /// A database that is lost when the app exits, for when the data directory is unusable
+    pub fn in_memory() -> Self {
+        Connection::open_in_memory()
+            .map_err(eyre::Report::from)
+            .and_then(Self::from_connection)
+            .expect("creating in-memory database")
+    }
+
+    fn from_connection(mut conn: Connection) -> eyre::Result<Self> {
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Every PR that was being watched or archived when the app last ran
+    pub fn stored_prs(&self) -> eyre::Result<Vec<StoredPr>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT host, owner, repo, pr_number, workflow_id, discovered, archived_at, final_status
+            FROM watched_prs ORDER BY added_at",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                WatchTarget {
+                    host: row.get(0)?,
+                    owner: row.get(1)?,
+                    repo: row.get(2)?,
+                    pr_number: row.get(3)?,
+                    workflow_id: row.get(4)?,
+                },
+                row.get::<_, bool>(5)?,
+                row.get::<_, Option<u64>>(6)?,
+                row.get::<_, Option<String>>(7)?,
+            ))
+        })?;
+
+        // a row that cannot be read is skipped, rather than losing every other PR with it
+        let mut prs = Vec::new();
+        for row in rows {
+            let (target, discovered, archived_at, final_status) = match row {
+                Ok(row) => row,
+                Err(e) => {
+                    tracing::warn!(error = ?e, "skipping unreadable stored pr");
+                    continue;
+                }
+            };
+            let archived = match (archived_at, final_status) {
+                (Some(at), Some(status)) => match parse_final_status(&status) {
+                    Ok(pr) => Some((at, pr)),
+                    Err(e) => {
+                        tracing::warn!(error = ?e, ?target, "skipping unreadable archived pr");
+                        continue;
+                    }
+                },
+                _ => None,
+            };
+            prs.push(StoredPr {
+                target,
+                discovered,
+                archived,
+            });
+        }
+        Ok(prs)
+    }
+
+    /// Record that a PR is watched, clearing any earlier archived state
+    ///
+    /// A PR the user watches is no longer dismissed, so discovery may find it again once the
+    /// user removes it.
+    pub fn watch(&self, target: &WatchTarget, discovered: bool, now: u64) -> eyre::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        delete_target(&conn, target)?;
+        if !discovered {
+            conn.execute(
+                "DELETE FROM dismissed_prs
+                WHERE host = ?1 AND owner = ?2 AND repo = ?3 AND pr_number = ?4",
+                params![target.host, target.owner, target.repo, target.pr_number],
+            )?;
+        }
+        conn.execute(
+            "INSERT INTO watched_prs (host, owner, repo, pr_number, workflow_id, discovered, added_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                target.host,
+                target.owner,
+                target.repo,
+                target.pr_number,
+                target.workflow_id,
+                discovered,
+                now
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Forget a watched or archived PR, keeping the history of its runs
+    pub fn unwatch(&self, target: &WatchTarget) -> eyre::Result<()> {
+        delete_target(&self.conn.lock().unwrap(), target)
+    }
+
+    /// Record that the user removed a discovered PR, so it is not discovered again
+    pub fn dismiss(&self, target: &WatchTarget, now: u64) -> eyre::Result<()> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO dismissed_prs (host, owner, repo, pr_number, dismissed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                target.host,
+                target.owner,
+                target.repo,
+                target.pr_number,
+                now
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Discovered PRs the user removed, for every workflow
+    pub fn dismissed_prs(&self) -> eyre::Result<Vec<WatchTarget>> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement =
+            conn.prepare("SELECT host, owner, repo, pr_number FROM dismissed_prs")?;
+        let targets = statement
+            .query_map([], |row| {
+                Ok(WatchTarget {
+                    host: row.get(0)?,
+                    owner: row.get(1)?,
+                    repo: row.get(2)?,
+                    pr_number: row.get(3)?,
+                    workflow_id: None,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(targets)
+    }
+
+    pub fn archive(&self, target: &WatchTarget, pr: &Pr, now: u64) -> eyre::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE watched_prs SET archived_at = ?1, final_status = ?2
+            WHERE host = ?3 AND owner = ?4 AND repo = ?5 AND pr_number = ?6
+                AND workflow_id IS ?7",
+            params![
+                now,
+                serde_json::to_string(&FinalStatus::new(pr))?,
+                target.host,
+                target.owner,
+                target.repo,
+                target.pr_number,
+                target.workflow_id
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_archive(&self) -> eyre::Result<()> {
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM watched_prs WHERE archived_at IS NOT NULL", [])?;
+        Ok(())
+    }
+
+    /// Typical duration of each job of a workflow, from its most recent successful runs
+    pub fn job_durations(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        workflow_id: u64,
+    ) -> eyre::Result<JobDurations> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT jobs.name, jobs.duration_secs FROM jobs
+            JOIN runs ON runs.host = jobs.host AND runs.id = jobs.run_id
+            WHERE runs.host = ?1 AND runs.id IN (
+                SELECT id FROM runs
+                WHERE host = ?1 AND owner = ?2 AND repo = ?3 AND workflow_id = ?4
+                    AND conclusion = 'succeeded'
+                ORDER BY completed_at DESC LIMIT ?5
+            ) AND jobs.duration_secs >= 0",
+        )?;
+        let samples = statement
+            .query_map(
+                params![host, owner, repo, workflow_id, DURATION_SAMPLE_RUNS],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?
+            .collect::<Result<Vec<(String, u64)>, _>>()?;
+        Ok(estimate::median_durations(samples))
+    }
+
+    /// Typical duration of each step of a workflow's jobs, from its most recent successful runs
+    pub fn step_durations(
+        &self,
+        host: &str,
+        owner: &str,
+        repo: &str,
+        workflow_id: u64,
+    ) -> eyre::Result<StepDurations> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = conn.prepare(
+            "SELECT jobs.name, steps.name, steps.duration_secs FROM steps
+            JOIN jobs ON jobs.host = steps.host AND jobs.id = steps.job_id
+            JOIN runs ON runs.host = jobs.host AND runs.id = jobs.run_id
+            WHERE runs.host = ?1 AND runs.id IN (
+                SELECT id FROM runs
+                WHERE host = ?1 AND owner = ?2 AND repo = ?3 AND workflow_id = ?4
+                    AND conclusion = 'succeeded'
+                ORDER BY completed_at DESC LIMIT ?5
+            ) AND steps.duration_secs >= 0",
+        )?;
+        let samples = statement
+            .query_map(
+                params![host, owner, repo, workflow_id, DURATION_SAMPLE_RUNS],
+                |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)),
+            )?
+            .collect::<Result<Vec<((String, String), u64)>, _>>()?;
+        Ok(estimate::median_durations(samples))
+    }
+
+    /// Record the runs and jobs of a polled PR, and the transition if its status changed
+    pub fn record(&self, event: &StatusEvent, now: u64) -> eyre::Result<()> {
+        let Some(pr) = &event.pr else {
+            return Ok(());
+        };
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for workflow in &pr.workflows {
+            record_run(&tx, &event.target, workflow, now)?;
+        }
+
+        let target = &event.target;
+        let previous: Option<String> = tx
+            .query_row(
+                "SELECT to_status FROM transitions
+                WHERE host = ?1 AND owner = ?2 AND repo = ?3 AND pr_number = ?4
+                    AND workflow_id IS ?5
+                ORDER BY id DESC LIMIT 1",
+                params![
+                    target.host,
+                    target.owner,
+                    target.repo,
+                    target.pr_number,
+                    target.workflow_id
+                ],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let status = status_name(&pr.status);
+        if previous.as_deref() != Some(status) {
+            tx.execute(
+                "INSERT INTO transitions
+                    (host, owner, repo, pr_number, workflow_id, from_status, to_status, at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    target.host,
+                    target.owner,
+                    target.repo,
+                    target.pr_number,
+                    target.workflow_id,
+                    previous,
+                    status,
+                    now
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn migrate(conn: &mut Connection) -> eyre::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > MIGRATIONS.len() {
+        eyre::bail!(
+            "database is at version {version}, newer than this version of the app supports"
+        );
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        tracing::debug!(version = %(i + 1), "migrating database");
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)
+            .wrap_err_with(|| format!("applying migration {}", i + 1))?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn delete_target(conn: &Connection, target: &WatchTarget) -> eyre::Result<()> {
+    conn.execute(
+        "DELETE FROM watched_prs
+        WHERE host = ?1 AND owner = ?2 AND repo = ?3 AND pr_number = ?4 AND workflow_id IS ?5",
+        params![
+            target.host,
+            target.owner,
+            target.repo,
+            target.pr_number,
+            target.workflow_id
+        ],
+    )?;
+    Ok(())
+}
+
+fn record_run(
+    conn: &Connection,
+    target: &WatchTarget,
+    workflow: &WorkflowStatus,
+    now: u64,
+) -> eyre::Result<()> {
+    let started_at = workflow
+        .jobs
+        .iter()
+        .map(|job| job.started_at.timestamp())
+        .min();
+    let conclusion = is_finished(&workflow.status).then(|| status_name(&workflow.status));
+    // a run is only complete once all of its jobs are
+    let completed_at = match conclusion {
+        Some(_) => workflow
+            .jobs
+            .iter()
+            .map(|job| job.completed_at.map(|at| at.timestamp()))
+            .collect::<Option<Vec<i64>>>()
+            .and_then(|times| times.into_iter().max()),
+        None => None,
+    };
+    conn.execute(
+        "INSERT INTO runs (host, id, owner, repo, pr_number, workflow_id, workflow_name, status,
+            conclusion, started_at, completed_at, first_seen_at, last_seen_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?12)
+        ON CONFLICT (host, id) DO UPDATE SET
+            status = excluded.status,
+            conclusion = excluded.conclusion,
+            started_at = excluded.started_at,
+            completed_at = excluded.completed_at,
+            last_seen_at = excluded.last_seen_at",
+        params![
+            target.host,
+            workflow.run_id,
+            target.owner,
+            target.repo,
+            target.pr_number,
+            workflow.workflow_id,
+            workflow.name,
+            status_name(&workflow.status),
+            conclusion,
+            started_at,
+            completed_at,
+            now
+        ],
+    )?;
+
+    for job in &workflow.jobs {
+        record_job(conn, &target.host, workflow.run_id, job)?;
+    }
+    Ok(())
+}
+
+fn record_job(conn: &Connection, host: &str, run_id: u64, job: &RunJob) -> eyre::Result<()> {
+    let started_at = job.started_at.timestamp();
+    let completed_at = job.completed_at.map(|at| at.timestamp());
+    // skewed clocks can put the end before the start
+    let duration_secs = job.duration().map(|d| d.num_seconds().max(0));
+    let conclusion = job
+        .conclusion
+        .map(|c| serde_json::to_value(c).map(|v| v.as_str().unwrap_or_default().to_string()))
+        .transpose()?;
+    conn.execute(
+        "INSERT INTO jobs (host, id, run_id, name, conclusion, started_at, completed_at,
+            duration_secs)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        ON CONFLICT (host, id) DO UPDATE SET
+            conclusion = excluded.conclusion,
+            started_at = excluded.started_at,
+            completed_at = excluded.completed_at,
+            duration_secs = excluded.duration_secs",
+        params![
+            host,
+            job.id,
+            run_id,
+            job.name,
+            conclusion,
+            started_at,
+            completed_at,
+            duration_secs
+        ],
+    )?;
+
+    for step in &job.steps {
+        let Some((started, completed)) = step.started_at.zip(step.completed_at) else {
+            continue;
+        };
+        conn.execute(
+            "INSERT OR REPLACE INTO steps (host, job_id, number, name, duration_secs)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                host,
+                job.id,
+                step.number,
+                step.name,
+                (completed - started).num_seconds().max(0)
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Name of the status without its progress, as stored in the database
+fn status_name(status: &Status) -> &'static str {
+    match status {
+        Status::Queued => "queued",
+        Status::Waiting => "waiting",
+        Status::InProgress(_) => "in_progress",
+        Status::Succeeded => "succeeded",
+        Status::Failed => "failed",
+        Status::Cancelled => "cancelled",
+        Status::Skipped => "skipped",
+        Status::TimedOut => "timed_out",
+        Status::ActionRequired => "action_required",
+        Status::Neutral => "neutral",
+        Status::Stale => "stale",
+        Status::StartupFailure => "startup_failure",
+        Status::Unknown => "unknown",
+    }
+}
+
+fn parse_status(name: &str, progress: Option<f32>) -> eyre::Result<Status> {
+    Ok(match name {
+        "queued" => Status::Queued,
+        "waiting" => Status::Waiting,
+        "in_progress" => Status::InProgress(progress.unwrap_or_default()),
+        "succeeded" => Status::Succeeded,
+        "failed" => Status::Failed,
+        "cancelled" => Status::Cancelled,
+        "skipped" => Status::Skipped,
+        "timed_out" => Status::TimedOut,
+        "action_required" => Status::ActionRequired,
+        "neutral" => Status::Neutral,
+        "stale" => Status::Stale,
+        "startup_failure" => Status::StartupFailure,
+        "unknown" => Status::Unknown,
+        _ => eyre::bail!("unknown status {name:?}"),
+    })
+}
+
+fn state_name(state: PrState) -> &'static str {
+    match state {
+        PrState::Open => "open",
+        PrState::Merged => "merged",
+        PrState::Closed => "closed",
+    }
+}
+
+fn parse_state(name: &str) -> eyre::Result<PrState> {
+    Ok(match name {
+        "open" => PrState::Open,
+        "merged" => PrState::Merged,
+        "closed" => PrState::Closed,
+        _ => eyre::bail!("unknown pr state {name:?}"),
+    })
+}
+
+fn parse_final_status(json: &str) -> eyre::Result<Pr> {
+    serde_json::from_str::<FinalStatus>(json)
+        .wrap_err("parsing archived status")?
+        .into_pr()
+}
+
+fn is_finished(status: &Status) -> bool {
+    !matches!(
+        status,
+        Status::Queued | Status::Waiting | Status::InProgress(_) | Status::Unknown
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fetcher::{Pr, Status, WorkflowStatus},
+        github::{JobStep, RunConclusion, RunJob, RunStatus},
+        watcher::{StatusEvent, WatchTarget},
+    };
+
+    use super::{History, StoredPr, MIGRATIONS};
+
+    fn target() -> WatchTarget {
+        WatchTarget {
+            host: "github.com".into(),
+            owner: "owner".into(),
+            repo: "repo".into(),
+            pr_number: 1,
+            workflow_id: None,
+        }
+    }
+
+    fn event(status: Status, completed_at: Option<&str>) -> StatusEvent {
+        let job = RunJob {
+            id: 1000,
+            name: "test".into(),
+            status: RunStatus::Completed,
+            conclusion: completed_at.map(|_| RunConclusion::Success),
+            created_at: None,
+            started_at: "2024-11-20T10:00:00Z".parse().unwrap(),
+            completed_at: completed_at.map(|at| at.parse().unwrap()),
+            steps: vec![JobStep {
+                name: "Run cargo test".into(),
+                number: 2,
+                status: RunStatus::Completed,
+                conclusion: completed_at.map(|_| RunConclusion::Success),
+                started_at: Some("2024-11-20T10:01:00Z".parse().unwrap()),
+                completed_at: completed_at.map(|at| at.parse().unwrap()),
+            }],
+        };
+        let workflow = WorkflowStatus::example(100, status, vec![job]);
+        StatusEvent::example(target(), Pr::example(status, vec![workflow]))
+    }
+
+    #[test]
+    fn migrates_once() {
+        let dir = tempfile::tempdir().unwrap();
+        History::open(dir.path()).unwrap();
+        let history = History::open(dir.path()).unwrap();
+        let version: usize = history
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+
+    #[test]
+    fn restores_watched_and_archived_prs() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(dir.path()).unwrap();
+        let discovered = WatchTarget {
+            pr_number: 2,
+            ..target()
+        };
+        history.watch(&target(), false, 1).unwrap();
+        history.watch(&discovered, true, 2).unwrap();
+        let pr = event(Status::Succeeded, None).pr.unwrap();
+        history.archive(&discovered, &pr, 3).unwrap();
+        drop(history);
+        // jobs are not kept in the archive
+        let mut archived = pr.clone();
+        archived.workflows[0].jobs.clear();
+
+        let history = History::open(dir.path()).unwrap();
+        assert_eq!(
+            history.stored_prs().unwrap(),
+            vec![
+                StoredPr {
+                    target: target(),
+                    discovered: false,
+                    archived: None,
+                },
+                StoredPr {
+                    target: discovered.clone(),
+                    discovered: true,
+                    archived: Some((3, archived)),
+                },
+            ]
+        );
+
+        // watching again brings it back out of the archive
+        history.watch(&discovered, false, 4).unwrap();
+        history.unwatch(&target()).unwrap();
+        assert_eq!(
+            history.stored_prs().unwrap(),
+            vec![StoredPr {
+                target: discovered,
+                discovered: false,
+                archived: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_unreadable_stored_prs() {
+        let history = History::in_memory();
+        let broken = WatchTarget {
+            pr_number: 2,
+            ..target()
+        };
+        history.watch(&broken, false, 1).unwrap();
+        history.watch(&target(), false, 2).unwrap();
+        history
+            .conn
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE watched_prs SET archived_at = 3, final_status = '{\"version\": \"0\"}'
+                WHERE pr_number = 2",
+                [],
+            )
+            .unwrap();
+
+        assert_eq!(
+            history.stored_prs().unwrap(),
+            vec![StoredPr {
+                target: target(),
+                discovered: false,
+                archived: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn restores_dismissed_prs() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(dir.path()).unwrap();
+        let other = WatchTarget {
+            pr_number: 2,
+            ..target()
+        };
+        history.dismiss(&target(), 1).unwrap();
+        history.dismiss(&other, 2).unwrap();
+        drop(history);
+
+        let history = History::open(dir.path()).unwrap();
+        let mut dismissed = history.dismissed_prs().unwrap();
+        dismissed.sort_by_key(|t| t.pr_number);
+        assert_eq!(dismissed, vec![target(), other.clone()]);
+
+        // the user watching it themselves undoes the dismissal, discovery does not
+        history.watch(&target(), true, 3).unwrap();
+        history.watch(&other, false, 3).unwrap();
+        assert_eq!(history.dismissed_prs().unwrap(), vec![target()]);
+    }
+
+    #[test]
+    fn ignores_negative_durations() {
+        let history = History::in_memory();
+        // the step completes before it started
+        history
+            .record(&event(Status::Succeeded, Some("2024-11-20T10:00:30Z")), 10)
+            .unwrap();
+        assert_eq!(
+            history
+                .step_durations("github.com", "owner", "repo", 10)
+                .unwrap(),
+            [(("test".to_string(), "Run cargo test".to_string()), 0)].into()
+        );
+
+        // rows recorded before durations were clamped
+        let conn = history.conn.lock().unwrap();
+        conn.execute("UPDATE jobs SET duration_secs = -30", [])
+            .unwrap();
+        conn.execute("UPDATE steps SET duration_secs = -30", [])
+            .unwrap();
+        drop(conn);
+        assert!(history
+            .job_durations("github.com", "owner", "repo", 10)
+            .unwrap()
+            .is_empty());
+        assert!(history
+            .step_durations("github.com", "owner", "repo", 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn records_runs_jobs_and_transitions() {
+        let history = History::in_memory();
+        history
+            .record(&event(Status::InProgress(0.5), None), 10)
+            .unwrap();
+        history
+            .record(&event(Status::InProgress(0.8), None), 20)
+            .unwrap();
+        history
+            .record(&event(Status::Succeeded, Some("2024-11-20T10:05:00Z")), 30)
+            .unwrap();
+
+        assert_eq!(
+            history
+                .job_durations("github.com", "owner", "repo", 10)
+                .unwrap(),
+            [("test".to_string(), 300)].into()
+        );
+
+        let conn = history.conn.lock().unwrap();
+        let run: (String, Option<String>, i64, Option<i64>, i64, i64) = conn
+            .query_row(
+                "SELECT status, conclusion, started_at, completed_at, first_seen_at, last_seen_at
+                FROM runs WHERE id = 100",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            run,
+            (
+                "succeeded".into(),
+                Some("succeeded".into()),
+                1732096800,
+                Some(1732097100),
+                10,
+                30
+            )
+        );
+
+        let job: (Option<String>, Option<i64>) = conn
+            .query_row(
+                "SELECT conclusion, duration_secs FROM jobs WHERE id = 1000",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(job, (Some("success".into()), Some(300)));
+        // steps keep the number GitHub gave them
+        let step_number: u64 = conn
+            .query_row("SELECT number FROM steps WHERE job_id = 1000", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(step_number, 2);
+        drop(conn);
+        assert_eq!(
+            history
+                .step_durations("github.com", "owner", "repo", 10)
+                .unwrap(),
+            [(("test".to_string(), "Run cargo test".to_string()), 240)].into()
+        );
+        let conn = history.conn.lock().unwrap();
+
+        // progress within a status is not a transition
+        let transitions: Vec<(Option<String>, String)> = conn
+            .prepare("SELECT from_status, to_status FROM transitions ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            transitions,
+            vec![
+                (None, "in_progress".into()),
+                (Some("in_progress".into()), "succeeded".into())
+            ]
+        );
+    }
+}
@@ -4,19 +4,46 @@
 use std::{
     collections::{hash_map::Entry, HashMap},
     sync::Arc,
+    time::Duration,
 };
 
 use color_eyre::eyre::{self, Context};
 
+mod cache;
+mod cli;
 mod config;
+mod error;
+mod estimate;
 mod fetcher;
+mod gh_cli;
 mod github;
+mod history;
+mod notifications;
+mod rate_limit;
+mod reference;
+mod retry;
+mod token;
+mod token_store;
+mod watcher;
 
-use config::AppConfig;
-use fetcher::{Fetcher, Pr};
-use github::WorkflowDetails;
+use config::{AppConfig, HostConfig, DEFAULT_HOST};
+use error::Error;
+use fetcher::Fetchers;
+use gh_cli::{GhAccount, GhCli};
+use github::{DevicePoll, WorkflowDetails};
+use history::History;
+use notifications::TransitionTracker;
+use rate_limit::RateLimit;
 use sentry::ClientInitGuard;
-use tauri::State;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+use tauri_plugin_notification::NotificationExt;
+use token::TokenReport;
+use token_store::{Credential, NamedCredential, ResolvedToken, TokenStore};
+use watcher::{
+    ArchivedPr, ExpiringToken, StatusEvent, WatchTarget, Watcher, DISCOVERED_PRS_CHANGED,
+    PR_STATUS_CHANGED,
+};
 
 #[cfg(debug_assertions)]
 use tauri::Manager;
@@ -25,44 +52,373 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct WorkflowCacheKey {
+    host: String,
     repo: String,
     owner: String,
 }
 
 struct AppState {
-    fetcher: Fetcher,
+    hosts: Vec<HostConfig>,
+    fetchers: Arc<Fetchers>,
+    tokens: Arc<TokenStore>,
+    watcher: Arc<Watcher>,
+    transitions: Arc<std::sync::Mutex<TransitionTracker>>,
     workflow_cache: Mutex<HashMap<WorkflowCacheKey, Vec<WorkflowDetails>>>,
+    /// Device codes of logins in progress, keyed by host
+    device_logins: std::sync::Mutex<HashMap<String, String>>,
+}
+
+impl AppState {
+    /// The token routed to repositories of `owner` on `host`
+    async fn token(&self, host: &str, owner: Option<&str>) -> error::Result<ResolvedToken> {
+        self.tokens
+            .resolve(host, owner)
+            .await
+            .ok_or_else(|| Error::NoToken {
+                host: host.to_string(),
+                owner: owner.map(str::to_string),
+            })
+    }
 }
 
 #[tauri::command]
-async fn fetch_status(
-    owner: String,
-    repo: String,
-    workflow_id: u64,
-    pr_number: u64,
+async fn set_token(
+    host: Option<String>,
+    token: String,
+    state: State<'_, AppState>,
+) -> error::Result<()> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, "storing token");
+    state
+        .tokens
+        .set(host, token)
+        .map_err(|e| Error::storage(&e))
+}
+
+#[tauri::command]
+async fn has_token(host: Option<String>, state: State<'_, AppState>) -> error::Result<bool> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    Ok(state.tokens.list().values().any(|c| c.host == host))
+}
+
+/// A stored credential, without its token
+#[derive(Serialize)]
+struct CredentialInfo {
+    name: String,
+    host: String,
+    /// Owners the credential is used for, every owner on the host if empty
+    owners: Vec<String>,
+    /// Whether the token is read from the gh CLI
+    gh_cli: bool,
+}
+
+#[tauri::command]
+async fn list_credentials(state: State<'_, AppState>) -> error::Result<Vec<CredentialInfo>> {
+    let credentials = state
+        .tokens
+        .list()
+        .into_iter()
+        .map(|(name, c)| CredentialInfo {
+            name,
+            host: c.host,
+            owners: c.owners,
+            gh_cli: matches!(c.credential, Credential::GhCli { .. }),
+        })
+        .collect();
+    Ok(credentials)
+}
+
+/// Store a named token, used for the repositories of `owners` or every owner if empty
+#[tauri::command]
+async fn add_credential(
+    name: String,
+    host: Option<String>,
+    owners: Vec<String>,
     token: String,
     state: State<'_, AppState>,
-) -> Result<Pr, String> {
-    tracing::debug!(%owner, %repo, %workflow_id, %pr_number, "requesting status");
-    let fetcher = &state.fetcher;
+) -> error::Result<()> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%name, %host, ?owners, "storing credential");
+    state.fetchers.get(&host)?;
+    let credential = NamedCredential {
+        host,
+        owners,
+        credential: Credential::Token(token),
+    };
+    state
+        .tokens
+        .insert(name, credential)
+        .map_err(|e| Error::storage(&e))
+}
+
+#[tauri::command]
+async fn remove_credential(name: String, state: State<'_, AppState>) -> error::Result<()> {
+    tracing::debug!(%name, "removing credential");
+    state.tokens.remove(&name).map_err(|e| Error::storage(&e))
+}
+
+#[tauri::command]
+async fn clear_token(host: Option<String>, state: State<'_, AppState>) -> error::Result<()> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, "clearing token");
+    state.tokens.clear(&host).map_err(|e| Error::storage(&e))
+}
+
+/// Hosts the gh CLI is logged in to that are also configured in this app
+#[tauri::command]
+async fn gh_cli_accounts(state: State<'_, AppState>) -> error::Result<Vec<GhAccount>> {
+    let accounts = GhCli::default()
+        .accounts()
+        .await
+        .into_iter()
+        .filter(|account| state.hosts.iter().any(|h| h.name == account.host))
+        .collect();
+    Ok(accounts)
+}
+
+/// Use the gh CLI's token for a host from now on, following it as it is rotated
+#[tauri::command]
+async fn use_gh_cli_token(host: Option<String>, state: State<'_, AppState>) -> error::Result<()> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, "using gh cli token");
+    state.fetchers.get(&host)?;
+    if GhCli::default().token(&host).await.is_none() {
+        return Err(Error::NoToken { host, owner: None });
+    }
+    let credential = NamedCredential::for_host(
+        host.clone(),
+        Credential::GhCli {
+            gh_cli_host: host.clone(),
+        },
+    );
+    state
+        .tokens
+        .insert(host, credential)
+        .map_err(|e| Error::storage(&e))
+}
+
+/// What the user needs to authorize the app, the device code itself stays in the backend
+#[derive(Serialize)]
+struct DeviceLogin {
+    user_code: String,
+    verification_uri: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum DeviceLoginStatus {
+    /// Poll again, after `interval` seconds if GitHub asked us to slow down
+    Pending { interval: Option<u64> },
+    /// The token has been stored
+    Complete,
+}
+
+#[tauri::command]
+async fn start_device_login(
+    host: Option<String>,
+    state: State<'_, AppState>,
+) -> error::Result<DeviceLogin> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, "starting device login");
+    let code = state.fetchers.get(&host)?.start_device_login().await?;
+    state
+        .device_logins
+        .lock()
+        .unwrap()
+        .insert(host, code.device_code);
+    Ok(DeviceLogin {
+        user_code: code.user_code,
+        verification_uri: code.verification_uri,
+        expires_in: code.expires_in,
+        interval: code.interval,
+    })
+}
+
+#[tauri::command]
+async fn poll_device_login(
+    host: Option<String>,
+    state: State<'_, AppState>,
+) -> error::Result<DeviceLoginStatus> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    let fetcher = state.fetchers.get(&host)?;
+    let device_code = state.device_logins.lock().unwrap().get(&host).cloned();
+    let Some(device_code) = device_code else {
+        return Err(Error::DeviceLoginExpired);
+    };
+
+    match fetcher.poll_device_login(&device_code).await {
+        Ok(DevicePoll::Pending { interval }) => Ok(DeviceLoginStatus::Pending { interval }),
+        Ok(DevicePoll::Complete { token }) => {
+            tracing::debug!(%host, "device login complete");
+            state.device_logins.lock().unwrap().remove(&host);
+            state
+                .tokens
+                .set(host, token)
+                .map_err(|e| Error::storage(&e))?;
+            Ok(DeviceLoginStatus::Complete)
+        }
+        Err(e) => {
+            // network errors are worth polling through, the rest end the login
+            if matches!(
+                e,
+                Error::DeviceLoginExpired | Error::AccessDenied | Error::OAuth { .. }
+            ) {
+                state.device_logins.lock().unwrap().remove(&host);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// A PR ready to watch, from a pasted URL or `owner/repo#123`
+#[derive(Serialize)]
+struct ParsedReference {
+    target: WatchTarget,
+    title: String,
+    /// Workflows that ran against the head of the PR
+    workflows: Vec<WorkflowDetails>,
+}
+
+#[tauri::command]
+async fn parse_github_reference(
+    input: String,
+    state: State<'_, AppState>,
+) -> error::Result<ParsedReference> {
+    tracing::debug!(%input, "parsing github reference");
+    let reference = reference::parse(&input, &state.hosts)?;
+    let host = reference.host().to_string();
+    let fetcher = state.fetchers.get(&host)?;
+    let resolved = state.token(&host, Some(reference.owner())).await?;
     let pr = fetcher
-        .fetch(token, owner, repo, workflow_id, pr_number)
+        .resolve_reference(&resolved.token, &reference)
         .await
-        .map_err(|e| format!("Error fetching pr status: {e}"))?;
-    tracing::debug!(?pr, "got pr");
-    Ok(pr)
+        .map_err(|e| e.for_credential(&resolved.credential))?;
+    Ok(ParsedReference {
+        target: WatchTarget {
+            host,
+            owner: pr.owner,
+            repo: pr.repo,
+            pr_number: pr.pr_number,
+            workflow_id: pr.workflow_id,
+        },
+        title: pr.title,
+        workflows: pr.workflows,
+    })
+}
+
+#[tauri::command]
+async fn list_hosts(state: State<'_, AppState>) -> error::Result<Vec<HostConfig>> {
+    Ok(state.hosts.clone())
+}
+
+#[tauri::command]
+async fn watch_pr(
+    host: Option<String>,
+    owner: String,
+    repo: String,
+    pr_number: u64,
+    workflow_id: Option<u64>,
+    state: State<'_, AppState>,
+) -> error::Result<()> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, %owner, %repo, ?workflow_id, %pr_number, "requesting watch");
+    let target = WatchTarget {
+        host,
+        owner,
+        repo,
+        pr_number,
+        workflow_id,
+    };
+    state.watcher.watch(target).await;
+    Ok(())
+}
+
+#[tauri::command]
+async fn unwatch_pr(
+    host: Option<String>,
+    owner: String,
+    repo: String,
+    pr_number: u64,
+    workflow_id: Option<u64>,
+    state: State<'_, AppState>,
+) -> error::Result<()> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, %owner, %repo, ?workflow_id, %pr_number, "requesting unwatch");
+    let target = WatchTarget {
+        host,
+        owner,
+        repo,
+        pr_number,
+        workflow_id,
+    };
+    state.watcher.unwatch(&target).await;
+    state.transitions.lock().unwrap().forget(&target);
+    Ok(())
+}
+
+/// PRs the user is watching, including ones restored from the history database
+#[tauri::command]
+async fn watched_prs(state: State<'_, AppState>) -> error::Result<Vec<WatchTarget>> {
+    Ok(state.watcher.targets().await)
+}
+
+#[tauri::command]
+async fn watched_pr_statuses(state: State<'_, AppState>) -> error::Result<Vec<StatusEvent>> {
+    Ok(state.watcher.statuses().await)
+}
+
+/// PRs watched because discovery found them, also sent with the `discovered-prs-changed` event
+#[tauri::command]
+async fn discovered_prs(state: State<'_, AppState>) -> error::Result<Vec<WatchTarget>> {
+    Ok(state.watcher.discovered().await)
+}
+
+/// Merged and closed PRs that are no longer polled, most recently archived first
+#[tauri::command]
+async fn archived_prs(state: State<'_, AppState>) -> error::Result<Vec<ArchivedPr>> {
+    Ok(state.watcher.archived_prs().await)
+}
+
+#[tauri::command]
+async fn clear_archived_prs(state: State<'_, AppState>) -> error::Result<()> {
+    state.watcher.clear_archive().await;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct RateLimitStatus {
+    /// Budget of each configured host, `None` until the first response from that host
+    rate_limits: HashMap<String, Option<RateLimit>>,
+    poll_interval_secs: u64,
+}
+
+#[tauri::command]
+async fn rate_limit_status(state: State<'_, AppState>) -> error::Result<RateLimitStatus> {
+    let rate_limits = state
+        .fetchers
+        .iter()
+        .map(|(host, fetcher)| (host.clone(), fetcher.rate_limit()))
+        .collect();
+    Ok(RateLimitStatus {
+        rate_limits,
+        poll_interval_secs: state.watcher.current_interval().as_secs(),
+    })
 }
 
 #[tauri::command]
 async fn fetch_workflows_for_repo(
+    host: Option<String>,
     owner: String,
     repo: String,
-    token: String,
     state: State<'_, AppState>,
-) -> Result<Vec<WorkflowDetails>, String> {
-    tracing::debug!(%owner, %repo, "requesting workflows for repo");
+) -> error::Result<Vec<WorkflowDetails>> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, %owner, %repo, "requesting workflows for repo");
 
     let cache_key = WorkflowCacheKey {
+        host: host.clone(),
         repo: repo.clone(),
         owner: owner.clone(),
     };
@@ -77,13 +433,14 @@ async fn fetch_workflows_for_repo(
         }
         Entry::Vacant(vacant_entry) => {
             tracing::trace!(?cache_key, "workflow cache miss");
-            let fetcher = &state.fetcher;
+            let fetcher = state.fetchers.get(&host)?;
+            let resolved = state.token(&host, Some(&owner)).await?;
             let workflows = fetcher
-                .fetch_workflows(token, &owner, &repo)
+                .fetch_workflows(resolved.token, &owner, &repo)
                 .await
-                .map_err(|e| {
+                .map_err(|e| e.for_credential(&resolved.credential))
+                .inspect_err(|e| {
                     tracing::warn!(error = %e, %owner, %repo, "error fetching workflows");
-                    format!("Error fetching workflows for '{owner}/{repo}': {e}")
                 })?;
             vacant_entry.insert(workflows.clone());
             Ok(workflows)
@@ -91,30 +448,185 @@ async fn fetch_workflows_for_repo(
     }
 }
 
+/// Check a token, warning with a notification if it expires soon
+///
+/// The stored token is checked if `token` is not given. If `owner` and `repo` are given
+/// the permissions the app needs are checked against that repository.
+#[tauri::command]
+async fn validate_token(
+    host: Option<String>,
+    token: Option<String>,
+    owner: Option<String>,
+    repo: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> error::Result<TokenReport> {
+    let host = host.unwrap_or_else(|| DEFAULT_HOST.to_string());
+    tracing::debug!(%host, ?owner, ?repo, "validating token");
+    let fetcher = state.fetchers.get(&host)?;
+    let (token, credential) = match token {
+        Some(token) => (token, None),
+        None => {
+            let resolved = state.token(&host, owner.as_deref()).await?;
+            (resolved.token, Some(resolved.credential))
+        }
+    };
+    let sample_repo = owner.as_deref().zip(repo.as_deref());
+    let report =
+        fetcher
+            .validate_token(&token, sample_repo)
+            .await
+            .map_err(|e| match &credential {
+                Some(credential) => e.for_credential(credential),
+                None => e,
+            })?;
+
+    if let (true, Some(expires_at)) = (report.expires_soon, report.expires_at) {
+        notify_token_expiring(&app, &report.login, &host, expires_at);
+    }
+    Ok(report)
+}
+
+/// Warn with a notification that the token for `name` on `host` expires soon
+fn notify_token_expiring<R: tauri::Runtime>(
+    app: &AppHandle<R>,
+    name: &str,
+    host: &str,
+    expires_at: i64,
+) {
+    let expires = chrono::DateTime::from_timestamp(expires_at, 0)
+        .map(|at| {
+            at.with_timezone(&chrono::Local)
+                .format("%d %b %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default();
+    tracing::info!(%expires, "token expires soon");
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("GitHub token expires soon")
+        .body(format!(
+            "The token for {name} on {host} expires {expires}, create a new one to keep watching PRs"
+        ))
+        .show()
+    {
+        tracing::warn!(error = %e, "error sending notification");
+    }
+}
+
 fn create_app<R: tauri::Runtime>(
     builder: tauri::Builder<R>,
-    base_url: impl Into<String>,
     app_config: Arc<AppConfig>,
+    tokens: Arc<TokenStore>,
+    history: Arc<History>,
 ) -> eyre::Result<tauri::App<R>> {
-    let fetcher = Fetcher::new(base_url, app_config);
+    let poll_interval = Duration::from_secs(app_config.poll_interval_secs);
+    let mut hosts = app_config.hosts();
+    let discovery = app_config.discovery.clone();
+    let archive_after = Duration::from_secs(app_config.archive_after_secs);
+    let progress_strategy = app_config.progress;
+    let fetchers = Arc::new(Fetchers::from_config(app_config));
+    // hosts that could not be set up have already been logged
+    hosts.retain(|host| fetchers.get(&host.name).is_ok());
+    let watcher = Arc::new(
+        Watcher::new(Arc::clone(&fetchers), Arc::clone(&tokens), poll_interval)
+            .with_discovery(discovery)
+            .with_archive_after(archive_after)
+            .with_progress_strategy(progress_strategy)
+            .with_history(history),
+    );
+    let transitions = Arc::new(std::sync::Mutex::new(TransitionTracker::default()));
     let app_state = AppState {
-        fetcher,
+        hosts,
+        fetchers,
+        tokens,
+        watcher: Arc::clone(&watcher),
+        transitions: Arc::clone(&transitions),
         workflow_cache: Default::default(),
+        device_logins: Default::default(),
     };
 
     builder
-        .setup(|app| {
+        .plugin(tauri_plugin_notification::init())
+        .setup(move |app| {
             #[cfg(debug_assertions)]
             {
                 let window = app.get_webview_window("main").unwrap();
                 window.open_devtools();
             }
+
+            let app_handle = app.handle().clone();
+            let discovered_handle = app.handle().clone();
+            let on_discovered = move |targets: Vec<WatchTarget>| {
+                if let Err(e) = discovered_handle.emit(DISCOVERED_PRS_CHANGED, targets) {
+                    tracing::warn!(error = %e, "error emitting discovered prs");
+                }
+            };
+            let on_change = move |event: &StatusEvent| {
+                if let Err(e) = app_handle.emit(PR_STATUS_CHANGED, event) {
+                    tracing::warn!(error = %e, "error emitting status event");
+                }
+
+                let notification = {
+                    let mut transitions = transitions.lock().unwrap();
+                    let notification = transitions.observe(event);
+                    // archived PRs are not polled again
+                    if event.archived {
+                        transitions.forget(&event.target);
+                    }
+                    notification
+                };
+                if let Some(notification) = notification {
+                    tracing::debug!(?notification, "sending notification");
+                    if let Err(e) = app_handle
+                        .notification()
+                        .builder()
+                        .title(notification.title)
+                        .body(notification.body)
+                        .show()
+                    {
+                        tracing::warn!(error = %e, "error sending notification");
+                    }
+                }
+            };
+            let expiry_handle = app.handle().clone();
+            // the token is checked on every poll, not just when the window first loads
+            let on_token_expiring = move |expiring: &ExpiringToken| {
+                notify_token_expiring(
+                    &expiry_handle,
+                    &expiring.credential,
+                    &expiring.host,
+                    expiring.expires_at,
+                );
+            };
+            tauri::async_runtime::spawn(watcher.run(on_change, on_discovered, on_token_expiring));
             Ok(())
         })
         .manage(app_state)
         .invoke_handler(tauri::generate_handler!(
-            fetch_status,
-            fetch_workflows_for_repo
+            set_token,
+            has_token,
+            clear_token,
+            list_credentials,
+            add_credential,
+            remove_credential,
+            gh_cli_accounts,
+            use_gh_cli_token,
+            start_device_login,
+            poll_device_login,
+            list_hosts,
+            parse_github_reference,
+            watch_pr,
+            unwatch_pr,
+            watched_prs,
+            watched_pr_statuses,
+            discovered_prs,
+            archived_prs,
+            clear_archived_prs,
+            rate_limit_status,
+            fetch_workflows_for_repo,
+            validate_token
         ))
         .build(tauri::generate_context!())
         .wrap_err("building tauri application")
@@ -139,28 +651,72 @@ fn init_sentry(enable: bool) -> Option<ClientInitGuard> {
     }
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
+fn init_tracing() {
     color_eyre::install().unwrap();
     tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .with(sentry::integrations::tracing::layer())
         .init();
+}
+
+/// Run a headless CLI command if one was given on the command line
+///
+/// Returns the process exit code, or `None` if the desktop app should be started instead.
+pub fn run_cli() -> Option<i32> {
+    let command = cli::Cli::parse_command(std::env::args_os()).unwrap_or_else(|e| e.exit())?;
+    init_tracing();
+    Some(cli::run(command))
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    init_tracing();
 
     let config = Arc::new(AppConfig::from_default_path().unwrap_or_default());
     tracing::debug!(?config, "loaded config");
 
     let _sentry_guard = init_sentry(config.enable_sentry);
 
-    let app = create_app(
-        tauri::Builder::default()
-            .plugin(tauri_plugin_shell::init())
-            .plugin(tauri_plugin_notification::init()),
-        "https://api.github.com",
+    let (tokens, tokens_error) = match config::config_dir().and_then(|dir| TokenStore::open(&dir)) {
+        Ok(tokens) => (tokens, None),
+        Err(e) => {
+            // saving a token reports the error rather than overwriting the stored tokens
+            tracing::error!(error = ?e, "error opening token store");
+            (TokenStore::unavailable(&e), Some(e))
+        }
+    };
+
+    let history = config::data_dir()
+        .and_then(|dir| History::open(&dir))
+        .unwrap_or_else(|e| {
+            tracing::error!(error = ?e, "error opening history database, history will not be saved");
+            History::in_memory()
+        });
+
+    let app = match create_app(
+        tauri::Builder::default().plugin(tauri_plugin_shell::init()),
         config,
-    )
-    .unwrap();
+        Arc::new(tokens),
+        Arc::new(history),
+    ) {
+        Ok(app) => app,
+        Err(e) => {
+            tracing::error!(error = ?e, "error starting application");
+            std::process::exit(1);
+        }
+    };
+    if let Some(e) = tokens_error {
+        if let Err(e) = app
+            .notification()
+            .builder()
+            .title("Stored tokens could not be read")
+            .body(format!("{e:#}"))
+            .show()
+        {
+            tracing::warn!(error = %e, "error sending notification");
+        }
+    }
     app.run(|_app_handle, _event| {});
 }
 
@@ -173,9 +729,11 @@ mod tests {
     use tauri::Manager;
 
     use crate::{
-        config::AppConfig,
+        config::{AppConfig, HostConfig},
         create_app,
         github::{GetWorkflowsResponse, WorkflowDetails},
+        history::History,
+        token_store::TokenStore,
     };
 
     fn init_logger() {
@@ -204,10 +762,20 @@ mod tests {
                 .body(serde_json::to_vec(&response).unwrap());
         });
 
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                api_base_url: server.base_url(),
+                ..HostConfig::github()
+            }],
+            ..Default::default()
+        };
+        let tokens = TokenStore::in_memory();
+        tokens.set("github.com", "abc123").unwrap();
         let app = create_app(
             tauri::test::mock_builder(),
-            server.base_url(),
-            Default::default(),
+            Arc::new(config),
+            Arc::new(tokens),
+            Arc::new(History::in_memory()),
         )
         .expect("creating mock app");
         let window = app.get_window("main").unwrap();
@@ -223,7 +791,6 @@ mod tests {
                 inner: serde_json::json!( {
                     "owner": "localstack",
                     "repo": "localstack",
-                    "token": "abc123",
                 }),
             },
             Ok(mock_workflows),
@@ -1,4 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 fn main() {
+    if let Some(code) = gh_actions_watch_lib::run_cli() {
+        std::process::exit(code);
+    }
     gh_actions_watch_lib::run()
 }
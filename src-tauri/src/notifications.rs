@@ -0,0 +1,136 @@
+use std::{collections::HashMap, mem::Discriminant};
+
+use crate::{
+    fetcher::Status,
+    watcher::{StatusEvent, WatchTarget},
+};
+
+/// A desktop notification to show the user
+#[derive(Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Observed {
+    /// Id and attempt of each run, as re-running a run keeps its id
+    runs: Vec<(u64, u64)>,
+    // ignore the progress of in-progress runs, only the state itself matters
+    status: Discriminant<Status>,
+}
+
+/// Detects real status transitions of watched PRs
+///
+/// Every PR is tracked by the runs (and their attempts) it is made up of along with their combined
+/// status, so a notification is produced exactly once per change. The first observation
+/// of a PR is used as a baseline and never notifies, so restarting the app does not
+/// replay notifications.
+#[derive(Default)]
+pub struct TransitionTracker {
+    observed: HashMap<WatchTarget, Observed>,
+}
+
+impl TransitionTracker {
+    pub fn observe(&mut self, event: &StatusEvent) -> Option<Notification> {
+        let pr = event.pr.as_ref()?;
+        let mut runs: Vec<(u64, u64)> = pr
+            .workflows
+            .iter()
+            .map(|w| (w.run_id, w.run_attempt))
+            .collect();
+        runs.sort_unstable();
+        let observed = Observed {
+            runs,
+            status: std::mem::discriminant(&pr.status),
+        };
+
+        match self.observed.insert(event.target.clone(), observed) {
+            None => {
+                tracing::trace!(target = ?event.target, "recording baseline status");
+                None
+            }
+            Some(previous) if previous == self.observed[&event.target] => None,
+            Some(_) => {
+                let WatchTarget {
+                    owner,
+                    repo,
+                    pr_number,
+                    ..
+                } = &event.target;
+                let description = match pr.status {
+                    Status::Queued => "queued",
+                    Status::Waiting => "waiting",
+                    Status::InProgress(_) => "in progress",
+                    Status::Succeeded => "succeeded!",
+                    Status::Failed => "failed",
+                    Status::Cancelled => "was cancelled",
+                    Status::Skipped => "was skipped",
+                    Status::TimedOut => "timed out",
+                    Status::ActionRequired => "requires action",
+                    Status::Neutral => "completed",
+                    Status::Stale => "is stale",
+                    Status::StartupFailure => "failed to start",
+                    Status::Unknown => return None,
+                };
+                Some(Notification {
+                    title: format!("{owner}/{repo}#{pr_number} {description}"),
+                    body: format!("{owner}/{repo}#{pr_number} ({}): {}", pr.branch, pr.title),
+                })
+            }
+        }
+    }
+
+    pub fn forget(&mut self, target: &WatchTarget) {
+        self.observed.remove(target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fetcher::{Pr, Status, WorkflowStatus},
+        watcher::{StatusEvent, WatchTarget},
+    };
+
+    use super::TransitionTracker;
+
+    fn event(run_attempt: u64, status: Status) -> StatusEvent {
+        let target = WatchTarget {
+            host: "github.com".into(),
+            owner: "owner".into(),
+            repo: "repo".into(),
+            pr_number: 1,
+            workflow_id: Some(10),
+        };
+        let workflow = WorkflowStatus {
+            run_attempt,
+            ..WorkflowStatus::example(100, status, Vec::new())
+        };
+        StatusEvent::example(target, Pr::example(status, vec![workflow]))
+    }
+
+    #[test]
+    fn notifies_once_per_transition() {
+        let mut tracker = TransitionTracker::default();
+
+        assert!(tracker.observe(&event(1, Status::Queued)).is_none());
+
+        let notification = tracker.observe(&event(1, Status::InProgress(0.1))).unwrap();
+        assert_eq!(notification.title, "owner/repo#1 in progress");
+        assert_eq!(notification.body, "owner/repo#1 (my-branch): My PR");
+
+        // progress alone is not a transition
+        assert!(tracker
+            .observe(&event(1, Status::InProgress(0.5)))
+            .is_none());
+
+        let notification = tracker.observe(&event(1, Status::Failed)).unwrap();
+        assert_eq!(notification.title, "owner/repo#1 failed");
+        assert!(tracker.observe(&event(1, Status::Failed)).is_none());
+
+        // a re-run keeps the run id, but one that failed again is still a new transition
+        assert!(tracker.observe(&event(2, Status::Failed)).is_some());
+        assert!(tracker.observe(&event(2, Status::Failed)).is_none());
+    }
+}
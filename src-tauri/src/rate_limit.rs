@@ -0,0 +1,344 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+use serde::Serialize;
+
+/// Snapshot of the GitHub API request budget, from the `x-ratelimit-*` response headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    /// Unix timestamp (in seconds) at which the budget is reset
+    pub reset: u64,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+        Some(Self {
+            limit: header("x-ratelimit-limit")?,
+            remaining: header("x-ratelimit-remaining")?,
+            reset: header("x-ratelimit-reset")?,
+        })
+    }
+}
+
+#[derive(Default)]
+struct State {
+    latest: Option<RateLimit>,
+    limited_until: Option<u64>,
+}
+
+/// Tracks the remaining request budget across every request made with a token
+#[derive(Default)]
+pub struct RateLimiter {
+    state: Mutex<State>,
+    requests: AtomicU64,
+}
+
+impl RateLimiter {
+    /// Check whether a request may be sent, returning the time the limit is lifted if not
+    pub fn check(&self, now: u64) -> Result<(), u64> {
+        let mut state = self.state.lock().unwrap();
+        match state.limited_until {
+            Some(until) if until > now => Err(until),
+            Some(_) => {
+                state.limited_until = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record a response, returning the time the limit is lifted if we have been rate limited
+    ///
+    /// Only the `core` budget is tracked. Other resources such as search have their own,
+    /// much smaller budgets, so being limited on one of those does not block other requests.
+    pub fn record(&self, status: StatusCode, headers: &HeaderMap, now: u64) -> Option<u64> {
+        let resource = headers
+            .get("x-ratelimit-resource")
+            .and_then(|v| v.to_str().ok());
+        let is_core = !matches!(resource, Some(resource) if resource != "core");
+
+        // conditional requests that hit the cache are free
+        if is_core && status != StatusCode::NOT_MODIFIED {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let rate_limit = RateLimit::from_headers(headers);
+        if is_core && rate_limit.is_some() {
+            state.latest = rate_limit;
+        }
+
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        // secondary rate limits send `retry-after`, primary rate limits exhaust the budget
+        let retry_after = headers
+            .get(RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let until = match (retry_after, rate_limit) {
+            (Some(retry_after), _) => now + retry_after,
+            (None, Some(rate_limit)) if rate_limit.remaining == 0 => rate_limit.reset,
+            // a 429 without any hints, back off for a minute
+            (None, _) if status == StatusCode::TOO_MANY_REQUESTS => now + 60,
+            // a plain permissions error
+            _ => return None,
+        };
+        tracing::warn!(%until, ?resource, "rate limited by GitHub");
+        if is_core {
+            state.limited_until = Some(until);
+        }
+        Some(until)
+    }
+
+    pub fn latest(&self) -> Option<RateLimit> {
+        self.state.lock().unwrap().latest
+    }
+
+    /// Total number of requests counted against the budget, used to measure how expensive a
+    /// poll is
+    pub fn request_count(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+}
+
+/// The budget of a token and how many requests have been counted against it so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBudget {
+    pub rate_limit: Option<RateLimit>,
+    pub requests: u64,
+}
+
+/// A [`RateLimiter`] for every token a client sends requests with
+///
+/// GitHub gives each token its own budget, so one credential being rate limited does not
+/// hold up requests made with the others. Limiters are keyed by a hash of the token so the
+/// tokens themselves are not kept around.
+#[derive(Default)]
+pub struct RateLimiters {
+    by_token: Mutex<HashMap<u64, Arc<RateLimiter>>>,
+}
+
+impl RateLimiters {
+    pub fn for_token(&self, token: &str) -> Arc<RateLimiter> {
+        let mut by_token = self.by_token.lock().unwrap();
+        Arc::clone(by_token.entry(token_key(token)).or_default())
+    }
+
+    /// The budget of the token with the fewest requests left
+    pub fn lowest(&self) -> Option<RateLimit> {
+        self.budgets()
+            .into_values()
+            .filter_map(|b| b.rate_limit)
+            .min_by_key(|rate_limit| rate_limit.remaining)
+    }
+
+    /// Budget of every token, keyed by the same opaque key across calls
+    pub fn budgets(&self) -> HashMap<u64, TokenBudget> {
+        self.by_token
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, limiter)| {
+                let budget = TokenBudget {
+                    rate_limit: limiter.latest(),
+                    requests: limiter.request_count(),
+                };
+                (*key, budget)
+            })
+            .collect()
+    }
+}
+
+/// Identifies a token without keeping the token itself around
+pub fn token_key(token: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    token.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Format a unix timestamp as `HH:MM` in the local timezone
+pub fn format_time(timestamp: u64) -> String {
+    let time = UNIX_EPOCH + Duration::from_secs(timestamp);
+    chrono::DateTime::<chrono::Local>::from(time)
+        .format("%H:%M")
+        .to_string()
+}
+
+/// Fraction of the budget kept back for requests made outside of polling
+const RESERVE_FRACTION: u64 = 10;
+
+/// How long to wait between polls so the remaining budget lasts until it is reset
+///
+/// This never polls faster than `base`, and slows down as the budget drops.
+pub fn adaptive_interval(
+    base: Duration,
+    rate_limit: Option<RateLimit>,
+    requests_per_poll: u64,
+    now: u64,
+) -> Duration {
+    let Some(rate_limit) = rate_limit else {
+        return base;
+    };
+    let until_reset = Duration::from_secs(rate_limit.reset.saturating_sub(now));
+
+    let usable = rate_limit
+        .remaining
+        .saturating_sub(rate_limit.limit / RESERVE_FRACTION);
+    let polls_left = usable / requests_per_poll.max(1);
+    if polls_left == 0 {
+        return base.max(until_reset);
+    }
+
+    base.max(until_reset / u32::try_from(polls_left).unwrap_or(u32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::{
+        header::{HeaderMap, HeaderValue},
+        StatusCode,
+    };
+
+    use super::{adaptive_interval, RateLimit, RateLimiter, RateLimiters};
+
+    fn headers(remaining: u64, reset: u64) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from(5000));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+        headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+        headers
+    }
+
+    #[test]
+    fn records_budget() {
+        let limiter = RateLimiter::default();
+        assert_eq!(
+            limiter.record(StatusCode::OK, &headers(4000, 2000), 1000),
+            None
+        );
+        assert_eq!(
+            limiter.latest(),
+            Some(RateLimit {
+                limit: 5000,
+                remaining: 4000,
+                reset: 2000,
+            })
+        );
+        assert_eq!(limiter.request_count(), 1);
+        assert!(limiter.check(1000).is_ok());
+    }
+
+    #[test]
+    fn exhausted_budget_blocks_until_reset() {
+        let limiter = RateLimiter::default();
+        assert_eq!(
+            limiter.record(StatusCode::FORBIDDEN, &headers(0, 2000), 1000),
+            Some(2000)
+        );
+        assert_eq!(limiter.check(1500), Err(2000));
+        assert!(limiter.check(2000).is_ok());
+    }
+
+    #[test]
+    fn tokens_have_separate_budgets() {
+        let limiters = RateLimiters::default();
+        limiters
+            .for_token("ghp_work")
+            .record(StatusCode::FORBIDDEN, &headers(0, 2000), 1000);
+        limiters
+            .for_token("ghp_personal")
+            .record(StatusCode::OK, &headers(4000, 2000), 1000);
+
+        assert_eq!(limiters.for_token("ghp_work").check(1500), Err(2000));
+        assert!(limiters.for_token("ghp_personal").check(1500).is_ok());
+        assert_eq!(limiters.lowest().map(|r| r.remaining), Some(0));
+        assert_eq!(limiters.budgets().len(), 2);
+    }
+
+    #[test]
+    fn other_resources_are_not_tracked() {
+        let limiter = RateLimiter::default();
+        let mut headers = headers(0, 2000);
+        headers.insert("x-ratelimit-limit", HeaderValue::from(30));
+        headers.insert("x-ratelimit-resource", HeaderValue::from_static("search"));
+        assert_eq!(
+            limiter.record(StatusCode::FORBIDDEN, &headers, 1000),
+            Some(2000)
+        );
+        assert_eq!(limiter.latest(), None);
+        assert_eq!(limiter.request_count(), 0);
+        assert!(limiter.check(1000).is_ok());
+    }
+
+    #[test]
+    fn secondary_limit_uses_retry_after() {
+        let limiter = RateLimiter::default();
+        let mut headers = headers(100, 2000);
+        headers.insert("retry-after", HeaderValue::from(30));
+        assert_eq!(
+            limiter.record(StatusCode::TOO_MANY_REQUESTS, &headers, 1000),
+            Some(1030)
+        );
+    }
+
+    #[test]
+    fn permission_error_is_not_a_rate_limit() {
+        let limiter = RateLimiter::default();
+        assert_eq!(
+            limiter.record(StatusCode::FORBIDDEN, &headers(100, 2000), 1000),
+            None
+        );
+        assert!(limiter.check(1000).is_ok());
+    }
+
+    #[test]
+    fn interval_slows_down_as_budget_drops() {
+        let base = Duration::from_secs(10);
+        let rate_limit = |remaining| {
+            Some(RateLimit {
+                limit: 5000,
+                remaining,
+                reset: 3600,
+            })
+        };
+
+        // plenty of budget left
+        assert_eq!(adaptive_interval(base, rate_limit(4500), 4, 0), base);
+        // 1000 usable requests at 60 requests per poll is 16 polls in an hour
+        assert_eq!(
+            adaptive_interval(base, rate_limit(1500), 60, 0),
+            Duration::from_secs(225)
+        );
+        // only the reserve is left
+        assert_eq!(
+            adaptive_interval(base, rate_limit(400), 60, 0),
+            Duration::from_secs(3600)
+        );
+        assert_eq!(adaptive_interval(base, None, 60, 0), base);
+    }
+}
@@ -0,0 +1,230 @@
+use reqwest::Url;
+
+use crate::{
+    config::{HostConfig, DEFAULT_HOST},
+    error::{self, Error},
+};
+
+/// Something on GitHub that identifies a PR, as pasted by the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitHubReference {
+    /// `https://github.com/owner/repo/pull/123` or `owner/repo#123`
+    PullRequest {
+        host: String,
+        owner: String,
+        repo: String,
+        number: u64,
+    },
+    /// `https://github.com/owner/repo/actions/runs/456`, optionally followed by a job
+    Run {
+        host: String,
+        owner: String,
+        repo: String,
+        run_id: u64,
+    },
+    /// `https://github.com/owner/repo/tree/some/branch`
+    Branch {
+        host: String,
+        owner: String,
+        repo: String,
+        branch: String,
+    },
+}
+
+impl GitHubReference {
+    pub fn host(&self) -> &str {
+        match self {
+            Self::PullRequest { host, .. } | Self::Run { host, .. } | Self::Branch { host, .. } => {
+                host
+            }
+        }
+    }
+
+    pub fn owner(&self) -> &str {
+        match self {
+            Self::PullRequest { owner, .. }
+            | Self::Run { owner, .. }
+            | Self::Branch { owner, .. } => owner,
+        }
+    }
+}
+
+/// Parse a PR, run or branch URL on one of the configured hosts, or `owner/repo#123`
+///
+/// The shorthand always refers to github.com.
+pub fn parse(input: &str, hosts: &[HostConfig]) -> error::Result<GitHubReference> {
+    let input = input.trim();
+    let invalid = || Error::InvalidReference {
+        input: input.to_string(),
+    };
+
+    if let Some(reference) = parse_shorthand(input) {
+        return Ok(reference);
+    }
+
+    // allow the scheme to be left off, as when copying from some address bars
+    let (url, has_scheme) = match Url::parse(input) {
+        Ok(url) => (url, true),
+        Err(_) => (
+            Url::parse(&format!("https://{input}")).map_err(|_| invalid())?,
+            false,
+        ),
+    };
+    let Some((host, path)) = hosts
+        .iter()
+        .find_map(|host| Some((host, strip_base(&url, &host.web_base_url)?)))
+    else {
+        // without a scheme, the input is more likely a typo than a URL on another site
+        if !has_scheme {
+            return Err(invalid());
+        }
+        return Err(Error::UnknownHost {
+            host: url.host_str().unwrap_or_default().to_string(),
+        });
+    };
+
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let [owner, repo, kind, rest @ ..] = segments.as_slice() else {
+        return Err(invalid());
+    };
+    let (host, owner, repo) = (host.name.clone(), owner.to_string(), repo.to_string());
+    match (*kind, rest) {
+        ("pull", [number, ..]) => Ok(GitHubReference::PullRequest {
+            host,
+            owner,
+            repo,
+            number: number.parse().map_err(|_| invalid())?,
+        }),
+        ("actions", ["runs", run_id, ..]) => Ok(GitHubReference::Run {
+            host,
+            owner,
+            repo,
+            run_id: run_id.parse().map_err(|_| invalid())?,
+        }),
+        // branch names may contain slashes
+        ("tree", branch) if !branch.is_empty() => Ok(GitHubReference::Branch {
+            host,
+            owner,
+            repo,
+            branch: branch.join("/"),
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_shorthand(input: &str) -> Option<GitHubReference> {
+    let (slug, number) = input.split_once('#')?;
+    let (owner, repo) = slug.split_once('/')?;
+    let valid = |s: &str| !s.is_empty() && !s.contains(['/', ':', ' ']);
+    if !valid(owner) || !valid(repo) {
+        return None;
+    }
+    Some(GitHubReference::PullRequest {
+        host: DEFAULT_HOST.to_string(),
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        number: number.parse().ok()?,
+    })
+}
+
+/// The path of `url` below `base`, if `url` is on the same site
+fn strip_base(url: &Url, base: &str) -> Option<String> {
+    let base = Url::parse(base).ok()?;
+    if url.host_str() != base.host_str()
+        || url.port_or_known_default() != base.port_or_known_default()
+    {
+        return None;
+    }
+    let path = url.path().strip_prefix(base.path().trim_end_matches('/'))?;
+    Some(path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::HostConfig, error::Error};
+
+    use super::{parse, GitHubReference};
+
+    fn hosts() -> Vec<HostConfig> {
+        vec![
+            HostConfig::github(),
+            HostConfig {
+                name: "ghe".into(),
+                api_base_url: "https://github.example.com/api/v3".into(),
+                web_base_url: "https://github.example.com".into(),
+                ca_bundle: None,
+                oauth_client_id: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn parses_references() {
+        let pr = GitHubReference::PullRequest {
+            host: "github.com".into(),
+            owner: "simonrw".into(),
+            repo: "gh-actions-monitor".into(),
+            number: 123,
+        };
+        for input in [
+            "simonrw/gh-actions-monitor#123",
+            "https://github.com/simonrw/gh-actions-monitor/pull/123",
+            " https://github.com/simonrw/gh-actions-monitor/pull/123/checks?check_run_id=1 ",
+            "github.com/simonrw/gh-actions-monitor/pull/123",
+        ] {
+            assert_eq!(parse(input, &hosts()).unwrap(), pr, "{input}");
+        }
+
+        assert_eq!(
+            parse(
+                "https://github.example.com/team/service/actions/runs/456/job/789",
+                &hosts()
+            )
+            .unwrap(),
+            GitHubReference::Run {
+                host: "ghe".into(),
+                owner: "team".into(),
+                repo: "service".into(),
+                run_id: 456,
+            }
+        );
+        assert_eq!(
+            parse(
+                "https://github.com/owner/repo/tree/feature/nested",
+                &hosts()
+            )
+            .unwrap(),
+            GitHubReference::Branch {
+                host: "github.com".into(),
+                owner: "owner".into(),
+                repo: "repo".into(),
+                branch: "feature/nested".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_other_input() {
+        for input in [
+            "owner/repo",
+            "owner/repo#abc",
+            "https://github.com/owner/repo",
+            "https://github.com/owner/repo/issues/1",
+            "https://github.com/owner/repo/tree/",
+        ] {
+            assert_eq!(
+                parse(input, &hosts()),
+                Err(Error::InvalidReference {
+                    input: input.into()
+                }),
+                "{input}"
+            );
+        }
+        assert_eq!(
+            parse("https://gitlab.com/owner/repo/pull/1", &hosts()),
+            Err(Error::UnknownHost {
+                host: "gitlab.com".into()
+            })
+        );
+    }
+}
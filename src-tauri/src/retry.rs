@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+
+/// How requests that fail for transient reasons are retried
+///
+/// Only idempotent requests are retried, and only after network errors or `5xx` responses.
+/// Delays grow exponentially from `base_delay_ms` up to `max_delay_ms`, with jitter so that
+/// many watched PRs do not retry in lockstep.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 10_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the given retry, starting at 1 for the first retry
+    ///
+    /// The delay is picked uniformly between half and all of the exponential backoff.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(31);
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay_ms);
+        Duration::from_millis(fastrand::u64(ceiling / 2..=ceiling))
+    }
+}
+
+/// Whether the outcome of sending a request is worth retrying
+pub fn is_transient(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error(),
+        // errors building the request or following redirects will fail again
+        Err(e) => !e.is_builder() && !e.is_redirect() && !e.is_status(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RetryPolicy;
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        for _ in 0..100 {
+            let first = policy.backoff(1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+            let third = policy.backoff(3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+            let capped = policy.backoff(20);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_millis(1000));
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use chrono::{DateTime, NaiveDateTime};
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+
+use crate::{
+    error::{self, Error},
+    github::{GitHubClient, User},
+    rate_limit::token_key,
+};
+
+/// Warn about tokens that expire within this many seconds
+pub const EXPIRY_WARNING_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Whether a token expiring at `expires_at` is due a warning
+pub fn expires_soon(expires_at: i64, now: i64) -> bool {
+    expires_at - now < EXPIRY_WARNING_SECS
+}
+
+/// When the tokens a client sends requests with expire, as reported by GitHub
+///
+/// GitHub reports the expiry of a token on every response to it, so a token nearing its
+/// expiry is noticed while polling. Keyed by a hash of the token, like [`RateLimiters`].
+///
+/// [`RateLimiters`]: crate::rate_limit::RateLimiters
+#[derive(Default)]
+pub struct TokenExpiries {
+    by_token: Mutex<HashMap<u64, i64>>,
+}
+
+impl TokenExpiries {
+    pub fn record(&self, token: &str, headers: &HeaderMap) {
+        if let Some(expires_at) = expiry(headers) {
+            self.by_token
+                .lock()
+                .unwrap()
+                .insert(token_key(token), expires_at);
+        }
+    }
+
+    /// Unix timestamp (in seconds) `token` expires at, `None` if it never does or no
+    /// response to it has been seen yet
+    pub fn expires_at(&self, token: &str) -> Option<i64> {
+        self.by_token
+            .lock()
+            .unwrap()
+            .get(&token_key(token))
+            .copied()
+    }
+}
+
+/// What GitHub reports about a token
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TokenReport {
+    pub login: String,
+    /// Scopes of a classic token, `None` for fine-grained tokens which do not report any
+    pub scopes: Option<Vec<String>>,
+    /// Unix timestamp (in seconds) at which the token expires, `None` if it never does
+    pub expires_at: Option<i64>,
+    pub expires_soon: bool,
+    /// Permissions checked against a sample repository, empty if none was given
+    pub permissions: Vec<PermissionCheck>,
+}
+
+/// Whether the token grants a permission the app needs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PermissionCheck {
+    /// e.g. `actions:read`
+    pub permission: &'static str,
+    pub granted: bool,
+    /// Set if the check failed for a reason other than a missing permission
+    pub error: Option<Error>,
+}
+
+/// Permissions the app needs, along with an endpoint that requires each of them
+const REQUIRED_PERMISSIONS: [(&str, &str); 2] = [
+    ("actions:read", "actions/workflows"),
+    ("contents:read", "pulls"),
+];
+
+/// Check a token is accepted, and that it can read the Actions data of a sample repository
+pub async fn validate(
+    client: &GitHubClient,
+    token: &str,
+    sample_repo: Option<(&str, &str)>,
+    now: i64,
+) -> error::Result<TokenReport> {
+    let (user, headers): (User, _) = client.get_with_headers("/user", token, None::<()>).await?;
+    let expires_at = expiry(&headers);
+
+    let mut permissions = Vec::new();
+    if let Some((owner, repo)) = sample_repo {
+        for (permission, endpoint) in REQUIRED_PERMISSIONS {
+            let result = client
+                .get::<serde_json::Value, _>(
+                    format!("/repos/{owner}/{repo}/{endpoint}"),
+                    token,
+                    Some([("per_page", 1)]),
+                )
+                .await;
+            let check = match result {
+                Ok(_) => PermissionCheck {
+                    permission,
+                    granted: true,
+                    error: None,
+                },
+                // fine-grained tokens without access to a repository cannot see it at all
+                Err(Error::Forbidden { .. } | Error::NotFound { .. }) => PermissionCheck {
+                    permission,
+                    granted: false,
+                    error: None,
+                },
+                Err(e) => PermissionCheck {
+                    permission,
+                    granted: false,
+                    error: Some(e),
+                },
+            };
+            permissions.push(check);
+        }
+    }
+
+    Ok(TokenReport {
+        login: user.login,
+        scopes: scopes(&headers),
+        expires_at,
+        expires_soon: expires_at.is_some_and(|at| expires_soon(at, now)),
+        permissions,
+    })
+}
+
+fn scopes(headers: &HeaderMap) -> Option<Vec<String>> {
+    let scopes = headers.get("x-oauth-scopes")?.to_str().ok()?;
+    Some(
+        scopes
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Parse the `github-authentication-token-expiration` header, e.g. `2024-11-30 12:00:00 UTC`
+fn expiry(headers: &HeaderMap) -> Option<i64> {
+    let value = headers
+        .get("github-authentication-token-expiration")?
+        .to_str()
+        .ok()?;
+    if let Ok(at) = DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S %z") {
+        return Some(at.timestamp());
+    }
+    let at =
+        NaiveDateTime::parse_from_str(value.trim_end_matches(" UTC"), "%Y-%m-%d %H:%M:%S").ok()?;
+    Some(at.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    use crate::github::GitHubClient;
+
+    use super::{expiry, validate, PermissionCheck, TokenExpiries};
+
+    #[test]
+    fn parse_expiry() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "github-authentication-token-expiration",
+            HeaderValue::from_static("2024-11-30 12:00:00 UTC"),
+        );
+        assert_eq!(expiry(&headers), Some(1_732_968_000));
+
+        headers.insert(
+            "github-authentication-token-expiration",
+            HeaderValue::from_static("2024-11-30 12:00:00 +0100"),
+        );
+        assert_eq!(expiry(&headers), Some(1_732_964_400));
+
+        assert_eq!(expiry(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn records_expiry_per_token() {
+        let expiries = TokenExpiries::default();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "github-authentication-token-expiration",
+            HeaderValue::from_static("2024-11-30 12:00:00 UTC"),
+        );
+        expiries.record("first", &headers);
+        // responses without the header do not forget the expiry
+        expiries.record("first", &HeaderMap::new());
+        expiries.record("second", &HeaderMap::new());
+
+        assert_eq!(expiries.expires_at("first"), Some(1_732_968_000));
+        assert_eq!(expiries.expires_at("second"), None);
+    }
+
+    #[tokio::test]
+    async fn reports_scopes_expiry_and_permissions() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/user");
+                then.status(200)
+                    .header("x-oauth-scopes", "repo, workflow")
+                    .header(
+                        "github-authentication-token-expiration",
+                        "2024-11-30 12:00:00 UTC",
+                    )
+                    .json_body(serde_json::json!({ "login": "simonrw" }));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/actions/workflows");
+                then.status(200)
+                    .json_body(serde_json::json!({ "total_count": 0, "workflows": [] }));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls");
+                then.status(403)
+                    .header("x-accepted-github-permissions", "contents=read");
+            })
+            .await;
+
+        let client = GitHubClient::new(server.base_url(), Default::default());
+        // two days before expiry
+        let now = 1_732_968_000 - 2 * 24 * 60 * 60;
+        let report = validate(&client, "abc123", Some(("owner", "repo")), now)
+            .await
+            .unwrap();
+
+        assert_eq!(report.login, "simonrw");
+        assert_eq!(
+            report.scopes,
+            Some(vec!["repo".to_string(), "workflow".to_string()])
+        );
+        assert_eq!(report.expires_at, Some(1_732_968_000));
+        assert!(report.expires_soon);
+        assert_eq!(
+            report.permissions,
+            vec![
+                PermissionCheck {
+                    permission: "actions:read",
+                    granted: true,
+                    error: None,
+                },
+                PermissionCheck {
+                    permission: "contents:read",
+                    granted: false,
+                    error: None,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,474 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use color_eyre::eyre::{self, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::gh_cli::GhCli;
+
+const TOKENS_FILE: &str = "tokens.enc";
+const KEY_FILE: &str = "tokens.key";
+const NONCE_LEN: usize = 12;
+
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "gh-actions-monitor";
+
+/// Where the tokens are persisted
+enum Backend {
+    #[cfg(feature = "keyring")]
+    Keyring(keyring::Entry),
+    File(EncryptedFile),
+    /// Tokens are lost when the store is dropped
+    Memory,
+    /// The persisted tokens could not be read, saving fails rather than overwriting them
+    Unavailable {
+        error: String,
+    },
+}
+
+/// How the token for a host is obtained
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Credential {
+    /// A token entered by the user
+    Token(String),
+    /// Whatever token the gh CLI has for a host, looked up every time it is needed so
+    /// `gh auth refresh` is picked up
+    GhCli { gh_cli_host: String },
+}
+
+/// A credential along with the repositories it is used for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamedCredential {
+    pub host: String,
+    /// Users or organizations this credential is used for, every owner on the host if empty
+    #[serde(default)]
+    pub owners: Vec<String>,
+    pub credential: Credential,
+}
+
+impl NamedCredential {
+    /// A credential for every owner on a host
+    pub fn for_host(host: impl Into<String>, credential: Credential) -> Self {
+        Self {
+            host: host.into(),
+            owners: Vec::new(),
+            credential,
+        }
+    }
+
+    fn is_for_owner(&self, owner: &str) -> bool {
+        self.owners.iter().any(|o| o.eq_ignore_ascii_case(owner))
+    }
+}
+
+/// Credentials as persisted, those stored before credentials were named are keyed by host
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredCredential {
+    Named(NamedCredential),
+    Legacy(Credential),
+}
+
+/// The token picked for a repository, along with the name of the credential it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedToken {
+    pub credential: String,
+    pub token: String,
+}
+
+/// Named GitHub credentials, kept on the Rust side so tokens never reach the webview
+///
+/// Tokens are stored in the OS keyring when built with the `keyring` feature and a keyring
+/// is available, and in an encrypted file in the config dir otherwise.
+pub struct TokenStore {
+    backend: Backend,
+    credentials: Mutex<BTreeMap<String, NamedCredential>>,
+    gh: GhCli,
+}
+
+impl TokenStore {
+    pub fn open(dir: &Path) -> eyre::Result<Self> {
+        #[cfg(feature = "keyring")]
+        match Self::open_keyring() {
+            Ok(store) => return Ok(store),
+            Err(e) => {
+                tracing::warn!(error = %e, "keyring unavailable, storing tokens in a file")
+            }
+        }
+        Self::open_file(dir)
+    }
+
+    /// Open the encrypted file backend, ignoring any keyring
+    pub fn open_file(dir: &Path) -> eyre::Result<Self> {
+        let file = EncryptedFile {
+            path: dir.join(TOKENS_FILE),
+            key_path: dir.join(KEY_FILE),
+        };
+        let credentials = match file.load()? {
+            Some(contents) => parse(&contents)?,
+            None => BTreeMap::new(),
+        };
+        Ok(Self {
+            backend: Backend::File(file),
+            credentials: Mutex::new(credentials),
+            gh: GhCli::default(),
+        })
+    }
+
+    #[cfg(feature = "keyring")]
+    fn open_keyring() -> eyre::Result<Self> {
+        let entry = keyring::Entry::new(KEYRING_SERVICE, "tokens")?;
+        let credentials = match entry.get_password() {
+            Ok(contents) => parse(&contents)?,
+            Err(keyring::Error::NoEntry) => BTreeMap::new(),
+            Err(e) => return Err(e).wrap_err("reading tokens from keyring"),
+        };
+        Ok(Self {
+            backend: Backend::Keyring(entry),
+            credentials: Mutex::new(credentials),
+            gh: GhCli::default(),
+        })
+    }
+
+    pub fn in_memory() -> Self {
+        Self {
+            backend: Backend::Memory,
+            credentials: Default::default(),
+            gh: GhCli::default(),
+        }
+    }
+
+    /// A store that failed to open, which reports `error` whenever tokens are saved
+    pub fn unavailable(error: &eyre::Report) -> Self {
+        Self {
+            backend: Backend::Unavailable {
+                error: format!("{error:#}"),
+            },
+            credentials: Default::default(),
+            gh: GhCli::default(),
+        }
+    }
+
+    /// The token to use for a repository owned by `owner` on `host`
+    ///
+    /// Credentials restricted to the owner win over those for the whole host, and among those
+    /// the credential named after the host (the one the login screen stores) comes first.
+    /// `None` if no credential matches, or the gh CLI has logged out of every one that does.
+    pub async fn resolve(&self, host: &str, owner: Option<&str>) -> Option<ResolvedToken> {
+        let credentials = self.credentials.lock().unwrap().clone();
+        let mut candidates: Vec<_> = credentials
+            .into_iter()
+            .filter(|(_, c)| c.host == host)
+            .filter(|(_, c)| c.owners.is_empty() || owner.is_some_and(|o| c.is_for_owner(o)))
+            .collect();
+        candidates.sort_by_key(|(name, c)| (c.owners.is_empty(), name != host));
+
+        for (name, c) in candidates {
+            if let Some(resolved) = self.token(name, c.credential).await {
+                return Some(resolved);
+            }
+        }
+        None
+    }
+
+    /// The token of every credential for a host
+    pub async fn host_tokens(&self, host: &str) -> Vec<ResolvedToken> {
+        let credentials = self.credentials.lock().unwrap().clone();
+        let mut tokens = Vec::new();
+        for (name, c) in credentials.into_iter().filter(|(_, c)| c.host == host) {
+            tokens.extend(self.token(name, c.credential).await);
+        }
+        tokens
+    }
+
+    async fn token(&self, name: String, credential: Credential) -> Option<ResolvedToken> {
+        let token = match credential {
+            Credential::Token(token) => Some(token),
+            Credential::GhCli { gh_cli_host } => self.gh.token(&gh_cli_host).await,
+        }?;
+        Some(ResolvedToken {
+            credential: name,
+            token,
+        })
+    }
+
+    /// Every stored credential, keyed by name
+    pub fn list(&self) -> BTreeMap<String, NamedCredential> {
+        self.credentials.lock().unwrap().clone()
+    }
+
+    /// Store a token for every owner on a host, replacing the one stored by a previous login
+    pub fn set(&self, host: impl Into<String>, token: impl Into<String>) -> eyre::Result<()> {
+        let host = host.into();
+        let credential = NamedCredential::for_host(host.clone(), Credential::Token(token.into()));
+        self.insert(host, credential)
+    }
+
+    pub fn insert(&self, name: impl Into<String>, credential: NamedCredential) -> eyre::Result<()> {
+        let mut credentials = self.credentials.lock().unwrap();
+        credentials.insert(name.into(), credential);
+        self.save(&credentials)
+    }
+
+    pub fn remove(&self, name: &str) -> eyre::Result<()> {
+        let mut credentials = self.credentials.lock().unwrap();
+        if credentials.remove(name).is_some() {
+            self.save(&credentials)?;
+        }
+        Ok(())
+    }
+
+    /// Remove every credential for a host
+    pub fn clear(&self, host: &str) -> eyre::Result<()> {
+        let mut credentials = self.credentials.lock().unwrap();
+        let before = credentials.len();
+        credentials.retain(|_, c| c.host != host);
+        if credentials.len() != before {
+            self.save(&credentials)?;
+        }
+        Ok(())
+    }
+
+    fn save(&self, credentials: &BTreeMap<String, NamedCredential>) -> eyre::Result<()> {
+        let contents = serde_json::to_string(credentials)?;
+        match &self.backend {
+            #[cfg(feature = "keyring")]
+            Backend::Keyring(entry) => entry
+                .set_password(&contents)
+                .wrap_err("writing tokens to keyring"),
+            Backend::File(file) => file.save(&contents),
+            Backend::Memory => Ok(()),
+            Backend::Unavailable { error } => {
+                eyre::bail!("the token store could not be opened, fix or remove it: {error}")
+            }
+        }
+    }
+}
+
+fn parse(contents: &str) -> eyre::Result<BTreeMap<String, NamedCredential>> {
+    let stored: BTreeMap<String, StoredCredential> =
+        serde_json::from_str(contents).wrap_err("parsing stored tokens")?;
+    let credentials = stored
+        .into_iter()
+        .map(|(name, stored)| {
+            let credential = match stored {
+                StoredCredential::Named(credential) => credential,
+                StoredCredential::Legacy(credential) => {
+                    NamedCredential::for_host(name.clone(), credential)
+                }
+            };
+            (name, credential)
+        })
+        .collect();
+    Ok(credentials)
+}
+
+/// A file encrypted with a key kept in a separate file
+///
+/// This keeps tokens out of plain text files and backups of the config file, but anyone
+/// able to read both files can decrypt it. Use the keyring backend where that matters.
+struct EncryptedFile {
+    path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl EncryptedFile {
+    fn load(&self) -> eyre::Result<Option<String>> {
+        let contents = match fs::read(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).wrap_err_with(|| format!("reading {}", self.path.display())),
+        };
+        if contents.len() < NONCE_LEN {
+            eyre::bail!("{} is truncated", self.path.display());
+        }
+        let (nonce, ciphertext) = contents.split_at(NONCE_LEN);
+        let cipher = ChaCha20Poly1305::new(&self.key()?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| eyre::eyre!("decrypting {}", self.path.display()))?;
+        let plaintext = String::from_utf8(plaintext).wrap_err("stored tokens are not UTF-8")?;
+        Ok(Some(plaintext))
+    }
+
+    fn save(&self, plaintext: &str) -> eyre::Result<()> {
+        let cipher = ChaCha20Poly1305::new(&self.key()?);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| eyre::eyre!("encrypting tokens"))?;
+        let mut contents = nonce.to_vec();
+        contents.extend(ciphertext);
+        write_private(&self.path, &contents)
+    }
+
+    /// Read the key, generating it the first time
+    fn key(&self) -> eyre::Result<Key> {
+        match fs::read(&self.key_path) {
+            Ok(key) if key.len() == 32 => Ok(*Key::from_slice(&key)),
+            Ok(_) => eyre::bail!("{} is not a valid key", self.key_path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::debug!(path = %self.key_path.display(), "generating token store key");
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private(&self.key_path, &key)?;
+                Ok(key)
+            }
+            Err(e) => Err(e).wrap_err_with(|| format!("reading {}", self.key_path.display())),
+        }
+    }
+}
+
+/// Write a file only the current user can read
+///
+/// The contents are written to a temporary file that then replaces `path`, so a crash
+/// part way through leaves the previous contents intact.
+fn write_private(path: &Path, contents: &[u8]) -> eyre::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .wrap_err_with(|| format!("creating directory {}", parent.display()))?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(&tmp_path)
+        .wrap_err_with(|| format!("opening {} for writing", tmp_path.display()))?;
+    std::io::Write::write_all(&mut file, contents)
+        .and_then(|_| file.sync_all())
+        .wrap_err_with(|| format!("writing {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path).wrap_err_with(|| format!("replacing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Credential, NamedCredential, ResolvedToken, TokenStore, TOKENS_FILE};
+
+    #[test]
+    fn reads_credentials_stored_by_host() {
+        let credentials = parse(
+            r#"{"github.com": "ghp_secret", "github.example.com": {"gh_cli_host": "github.example.com"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            credentials["github.com"],
+            NamedCredential::for_host("github.com", Credential::Token("ghp_secret".into()))
+        );
+        assert_eq!(
+            credentials["github.example.com"],
+            NamedCredential::for_host(
+                "github.example.com",
+                Credential::GhCli {
+                    gh_cli_host: "github.example.com".into()
+                }
+            )
+        );
+
+        let contents = serde_json::to_string(&credentials).unwrap();
+        assert_eq!(parse(&contents).unwrap(), credentials);
+    }
+
+    #[tokio::test]
+    async fn routes_by_owner_then_host() {
+        let store = TokenStore::in_memory();
+        store.set("github.com", "ghp_default").unwrap();
+        store
+            .insert(
+                "work",
+                NamedCredential {
+                    host: "github.com".into(),
+                    owners: vec!["my-company".into()],
+                    credential: Credential::Token("ghp_work".into()),
+                },
+            )
+            .unwrap();
+        store
+            .insert(
+                "other",
+                NamedCredential::for_host("github.com", Credential::Token("ghp_other".into())),
+            )
+            .unwrap();
+
+        let store = &store;
+        let resolve = |owner| async move { store.resolve("github.com", owner).await.unwrap() };
+        assert_eq!(
+            resolve(Some("My-Company")).await,
+            ResolvedToken {
+                credential: "work".into(),
+                token: "ghp_work".into()
+            }
+        );
+        assert_eq!(resolve(Some("simonrw")).await.credential, "github.com");
+        assert_eq!(resolve(None).await.credential, "github.com");
+        assert_eq!(
+            store
+                .resolve("github.example.com", Some("my-company"))
+                .await,
+            None
+        );
+
+        store.clear("github.com").unwrap();
+        assert!(store.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn tokens_persist_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let store = TokenStore::open_file(dir.path()).unwrap();
+        assert_eq!(store.resolve("github.com", None).await, None);
+        store.set("github.com", "ghp_secret").unwrap();
+        store.set("github.example.com", "ghp_other").unwrap();
+
+        let contents = std::fs::read(dir.path().join(TOKENS_FILE)).unwrap();
+        assert!(!String::from_utf8_lossy(&contents).contains("ghp_secret"));
+
+        async fn token(store: &TokenStore, host: &str) -> Option<String> {
+            store.resolve(host, None).await.map(|r| r.token)
+        }
+        let store = TokenStore::open_file(dir.path()).unwrap();
+        assert_eq!(
+            token(&store, "github.com").await.as_deref(),
+            Some("ghp_secret")
+        );
+        store.clear("github.com").unwrap();
+
+        let store = TokenStore::open_file(dir.path()).unwrap();
+        assert_eq!(token(&store, "github.com").await, None);
+        assert_eq!(
+            token(&store, "github.example.com").await.as_deref(),
+            Some("ghp_other")
+        );
+        assert!(!dir.path().join(format!("{TOKENS_FILE}.tmp")).exists());
+    }
+
+    #[test]
+    fn unreadable_store_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(TOKENS_FILE);
+        TokenStore::open_file(dir.path())
+            .unwrap()
+            .set("github.com", "ghp_secret")
+            .unwrap();
+        std::fs::write(&path, b"corrupt").unwrap();
+
+        let error = TokenStore::open_file(dir.path()).err().unwrap();
+        let store = TokenStore::unavailable(&error);
+        let error = store.set("github.com", "ghp_new").unwrap_err();
+        assert!(error.to_string().contains("could not be opened"), "{error}");
+        assert_eq!(std::fs::read(&path).unwrap(), b"corrupt");
+    }
+}
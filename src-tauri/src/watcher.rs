@@ -0,0 +1,1049 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+
+use crate::{
+    config::{DiscoveryConfig, ProgressStrategy, DEFAULT_ARCHIVE_AFTER_SECS},
+    error::{self, Error},
+    estimate::{self, JobDurations, StepDurations, WorkflowDurations},
+    fetcher::{Fetcher, Fetchers, Pr, PrState},
+    history::History,
+    rate_limit::{self, TokenBudget},
+    token,
+    token_store::{ResolvedToken, TokenStore},
+};
+
+/// Name of the event emitted to the frontend when the status of a watched PR changes
+pub const PR_STATUS_CHANGED: &str = "pr-status-changed";
+
+/// Name of the event emitted to the frontend with the PRs found by discovery, whenever they change
+pub const DISCOVERED_PRS_CHANGED: &str = "discovered-prs-changed";
+
+/// A PR (and optionally a single workflow) to poll
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchTarget {
+    /// Name of the configured host the repository lives on
+    pub host: String,
+    pub owner: String,
+    pub repo: String,
+    pub pr_number: u64,
+    /// `None` watches every workflow that ran against the PR
+    pub workflow_id: Option<u64>,
+}
+
+impl WatchTarget {
+    /// Whether both targets are for the same PR, whichever workflows they watch
+    fn is_same_pr(&self, other: &WatchTarget) -> bool {
+        self.host == other.host
+            && self.owner == other.owner
+            && self.repo == other.repo
+            && self.pr_number == other.pr_number
+    }
+}
+
+/// Payload of the [`PR_STATUS_CHANGED`] event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatusEvent {
+    pub target: WatchTarget,
+    pub pr: Option<Pr>,
+    pub error: Option<Error>,
+    /// The PR has been merged or closed for long enough that it is no longer polled
+    pub archived: bool,
+}
+
+#[cfg(test)]
+impl StatusEvent {
+    pub fn example(target: WatchTarget, pr: Pr) -> Self {
+        Self {
+            target,
+            pr: Some(pr),
+            error: None,
+            archived: false,
+        }
+    }
+}
+
+/// Host, owner, repository and id of a workflow
+type WorkflowKey = (String, String, String, u64);
+
+struct WatchedPr {
+    last: Option<StatusEvent>,
+    /// Added by discovery rather than the user
+    discovered: bool,
+}
+
+/// A token found to expire soon while polling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringToken {
+    pub host: String,
+    /// Name of the credential the token is stored under
+    pub credential: String,
+    /// Unix timestamp (in seconds)
+    pub expires_at: i64,
+}
+
+/// A merged or closed PR that is no longer polled, with its final status
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchivedPr {
+    pub target: WatchTarget,
+    pub pr: Pr,
+    /// Unix timestamp (in seconds) the PR was archived at
+    pub archived_at: u64,
+}
+
+/// Background scheduler that owns the list of watched PRs and polls them
+pub struct Watcher {
+    fetchers: Arc<Fetchers>,
+    tokens: Arc<TokenStore>,
+    watched: Mutex<HashMap<WatchTarget, WatchedPr>>,
+    /// Always locked after `watched` when both are needed
+    archived: Mutex<HashMap<WatchTarget, ArchivedPr>>,
+    /// Discovered PRs the user removed, which discovery skips from then on
+    ///
+    /// Always locked after `watched` and `archived`.
+    dismissed: Mutex<HashSet<WatchTarget>>,
+    /// How long a merged or closed PR keeps being polled before it is archived
+    archive_after: Duration,
+    poll_interval: Duration,
+    progress_strategy: ProgressStrategy,
+    history: Arc<History>,
+    /// Durations of recent successful runs fetched for workflows without any local history
+    api_durations: Mutex<HashMap<WorkflowKey, (JobDurations, StepDurations)>>,
+    /// Interval currently in use, slowed down from `poll_interval` to stay within the rate limit
+    current_interval_secs: AtomicU64,
+    /// Tokens already warned about, keyed like [`rate_limit::token_key`]
+    warned_tokens: Mutex<HashSet<u64>>,
+    /// Tokens found to expire soon that have not been reported yet
+    expiring_tokens: Mutex<Vec<ExpiringToken>>,
+    wake: Notify,
+    discovery: DiscoveryConfig,
+}
+
+impl Watcher {
+    pub fn new(fetchers: Arc<Fetchers>, tokens: Arc<TokenStore>, poll_interval: Duration) -> Self {
+        Self {
+            fetchers,
+            tokens,
+            watched: Default::default(),
+            archived: Default::default(),
+            dismissed: Default::default(),
+            archive_after: Duration::from_secs(DEFAULT_ARCHIVE_AFTER_SECS),
+            poll_interval,
+            progress_strategy: ProgressStrategy::default(),
+            history: Arc::new(History::in_memory()),
+            api_durations: Default::default(),
+            current_interval_secs: AtomicU64::new(poll_interval.as_secs()),
+            warned_tokens: Default::default(),
+            expiring_tokens: Default::default(),
+            wake: Notify::new(),
+            discovery: DiscoveryConfig::default(),
+        }
+    }
+
+    /// Also watch the open PRs of the logged in user
+    pub fn with_discovery(mut self, discovery: DiscoveryConfig) -> Self {
+        self.discovery = discovery;
+        self
+    }
+
+    pub fn with_archive_after(mut self, archive_after: Duration) -> Self {
+        self.archive_after = archive_after;
+        self
+    }
+
+    pub fn with_progress_strategy(mut self, progress_strategy: ProgressStrategy) -> Self {
+        self.progress_strategy = progress_strategy;
+        self
+    }
+
+    /// Persist watched PRs and their runs, resuming with the PRs stored from the last run
+    pub fn with_history(mut self, history: Arc<History>) -> Self {
+        let stored = history.stored_prs().unwrap_or_else(|e| {
+            tracing::warn!(error = ?e, "error reading stored prs");
+            Vec::new()
+        });
+        let mut watched = HashMap::new();
+        let mut archived = HashMap::new();
+        for pr in stored {
+            match pr.archived {
+                Some((archived_at, last)) => {
+                    archived.insert(
+                        pr.target.clone(),
+                        ArchivedPr {
+                            target: pr.target,
+                            pr: last,
+                            archived_at,
+                        },
+                    );
+                }
+                None => {
+                    watched.insert(
+                        pr.target,
+                        WatchedPr {
+                            last: None,
+                            discovered: pr.discovered,
+                        },
+                    );
+                }
+            }
+        }
+        let dismissed: HashSet<WatchTarget> = history
+            .dismissed_prs()
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = ?e, "error reading dismissed prs");
+                Vec::new()
+            })
+            .into_iter()
+            .collect();
+        tracing::debug!(
+            watched = %watched.len(),
+            archived = %archived.len(),
+            dismissed = %dismissed.len(),
+            "restored prs"
+        );
+        self.watched = Mutex::new(watched);
+        self.archived = Mutex::new(archived);
+        self.dismissed = Mutex::new(dismissed);
+        self.history = history;
+        self
+    }
+
+    /// Start watching a PR, polling it straight away
+    pub async fn watch(&self, target: WatchTarget) {
+        tracing::debug!(?target, "watching pr");
+        let mut watched = self.watched.lock().await;
+        let entry = watched.entry(target.clone()).or_insert(WatchedPr {
+            last: None,
+            discovered: false,
+        });
+        // the user wants to keep this one
+        entry.discovered = false;
+        self.archived.lock().await.remove(&target);
+        self.dismissed
+            .lock()
+            .await
+            .retain(|dismissed| !dismissed.is_same_pr(&target));
+        drop(watched);
+
+        let now = rate_limit::unix_now();
+        let stored = target.clone();
+        let result = self
+            .in_background(move |history| history.watch(&stored, false, now))
+            .await;
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, ?target, "error storing watched pr");
+        }
+        self.wake.notify_one();
+    }
+
+    pub async fn unwatch(&self, target: &WatchTarget) {
+        tracing::debug!(?target, "unwatching pr");
+        let mut watched = self.watched.lock().await;
+        let removed = watched.remove(target);
+        self.archived.lock().await.remove(target);
+        // otherwise the next search would add it straight back
+        let dismiss = removed.is_some_and(|w| w.discovered);
+        if dismiss {
+            self.dismissed.lock().await.insert(target.clone());
+        }
+        drop(watched);
+
+        let now = rate_limit::unix_now();
+        let stored = target.clone();
+        let result = self
+            .in_background(move |history| {
+                history.unwatch(&stored)?;
+                if dismiss {
+                    history.dismiss(&stored, now)?;
+                }
+                Ok(())
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, ?target, "error removing stored pr");
+        }
+    }
+
+    /// Every PR the user asked to watch, whether or not it has been polled yet
+    pub async fn targets(&self) -> Vec<WatchTarget> {
+        self.watched
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, w)| !w.discovered)
+            .map(|(target, _)| target.clone())
+            .collect()
+    }
+
+    /// The most recent status of every watched PR that has been polled at least once
+    pub async fn statuses(&self) -> Vec<StatusEvent> {
+        self.watched
+            .lock()
+            .await
+            .values()
+            .filter_map(|w| w.last.clone())
+            .collect()
+    }
+
+    /// PRs currently watched because discovery found them
+    pub async fn discovered(&self) -> Vec<WatchTarget> {
+        self.watched
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, w)| w.discovered)
+            .map(|(target, _)| target.clone())
+            .collect()
+    }
+
+    /// PRs that are no longer polled because they were merged or closed, most recent first
+    pub async fn archived_prs(&self) -> Vec<ArchivedPr> {
+        let mut archived: Vec<ArchivedPr> = self.archived.lock().await.values().cloned().collect();
+        archived.sort_by_key(|a| std::cmp::Reverse(a.archived_at));
+        archived
+    }
+
+    pub async fn clear_archive(&self) {
+        self.archived.lock().await.clear();
+        let result = self.in_background(|history| history.clear_archive()).await;
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, "error clearing stored archive");
+        }
+    }
+
+    /// Search every host for open PRs by the logged in user, watching new ones, returning
+    /// whether any were found
+    ///
+    /// Each credential for a host is searched, as fine-grained tokens only see some owners.
+    /// PRs that are no longer open are left to be archived by [`Watcher::poll`].
+    pub async fn discover(&self) -> bool {
+        let mut found: HashSet<WatchTarget> = HashSet::new();
+        for (host, fetcher) in self.fetchers.iter() {
+            for resolved in self.tokens.host_tokens(host).await {
+                let result = fetcher
+                    .search_my_prs(&resolved.token, &self.discovery.orgs, &self.discovery.repos)
+                    .await;
+                match result {
+                    Ok(prs) => found.extend(prs.into_iter().map(|pr| WatchTarget {
+                        host: host.clone(),
+                        owner: pr.owner,
+                        repo: pr.repo,
+                        pr_number: pr.number,
+                        workflow_id: None,
+                    })),
+                    Err(e) => {
+                        let e = e.for_credential(&resolved.credential);
+                        tracing::warn!(error = %e, %host, "error searching for open prs");
+                    }
+                }
+            }
+        }
+        tracing::debug!(num_prs = %found.len(), "discovered open prs");
+
+        let mut watched = self.watched.lock().await;
+        let archived = self.archived.lock().await;
+        let dismissed = self.dismissed.lock().await;
+        let mut added = Vec::new();
+        for target in found {
+            // search results lag behind, so a PR archived since may still be listed
+            if watched
+                .keys()
+                .chain(archived.keys())
+                .chain(dismissed.iter())
+                .any(|t| t.is_same_pr(&target))
+            {
+                continue;
+            }
+            tracing::debug!(?target, "watching discovered pr");
+            watched.insert(
+                target.clone(),
+                WatchedPr {
+                    last: None,
+                    discovered: true,
+                },
+            );
+            added.push(target);
+        }
+        drop(dismissed);
+        drop(archived);
+        drop(watched);
+
+        let changed = !added.is_empty();
+        let now = rate_limit::unix_now();
+        let result = self
+            .in_background(move |history| {
+                added
+                    .iter()
+                    .try_for_each(|target| history.watch(target, true, now))
+            })
+            .await;
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, "error storing discovered prs");
+        }
+        changed
+    }
+
+    /// Interval between polls, taking the remaining rate limit budget into account
+    pub fn current_interval(&self) -> Duration {
+        Duration::from_secs(self.current_interval_secs.load(Ordering::Relaxed))
+    }
+
+    /// Poll every watched PR, calling `on_change` for each one whose status changed
+    ///
+    /// With discovery enabled, `on_discovered` is called with the discovered PRs whenever
+    /// they change.
+    pub async fn run(
+        self: Arc<Self>,
+        mut on_change: impl FnMut(&StatusEvent),
+        mut on_discovered: impl FnMut(Vec<WatchTarget>),
+        mut on_token_expiring: impl FnMut(&ExpiringToken),
+    ) {
+        let discovery_interval = Duration::from_secs(self.discovery.interval_secs);
+        let mut next_discovery = Instant::now();
+        loop {
+            // searches are counted against their own budget, so do not affect the poll interval
+            if self.discovery.enabled && Instant::now() >= next_discovery {
+                next_discovery = Instant::now() + discovery_interval;
+                if self.discover().await {
+                    on_discovered(self.discovered().await);
+                }
+            }
+
+            let requests_before: HashMap<&String, HashMap<u64, TokenBudget>> = self
+                .fetchers
+                .iter()
+                .map(|(host, fetcher)| (host, fetcher.budgets()))
+                .collect();
+            for event in self.poll().await {
+                on_change(&event);
+            }
+            for expiring in self.take_expiring_tokens().await {
+                on_token_expiring(&expiring);
+            }
+
+            // every token has its own budget, poll at the pace of the most constrained one
+            let now = rate_limit::unix_now();
+            let base = self.poll_interval;
+            let interval = self
+                .fetchers
+                .iter()
+                .flat_map(|(host, fetcher)| {
+                    let before = &requests_before[host];
+                    fetcher.budgets().into_iter().map(move |(token, budget)| {
+                        let requests_before = before.get(&token).map_or(0, |b| b.requests);
+                        rate_limit::adaptive_interval(
+                            base,
+                            budget.rate_limit,
+                            budget.requests - requests_before,
+                            now,
+                        )
+                    })
+                })
+                .max()
+                .unwrap_or(self.poll_interval);
+            if interval != self.current_interval() {
+                tracing::info!(?interval, "adjusting poll interval");
+                self.current_interval_secs
+                    .store(interval.as_secs(), Ordering::Relaxed);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {},
+                _ = self.wake.notified() => {
+                    tracing::trace!("watcher woken early");
+                },
+            }
+        }
+    }
+
+    /// Poll every watched PR once, returning the events for PRs whose status changed
+    ///
+    /// PRs that have been merged or closed for longer than the grace period are moved to the
+    /// archive, with a final event that has `archived` set.
+    pub async fn poll(&self) -> Vec<StatusEvent> {
+        let now = rate_limit::unix_now();
+        // do not hold the lock while talking to GitHub
+        let targets: Vec<WatchTarget> = self.watched.lock().await.keys().cloned().collect();
+
+        let mut events = Vec::new();
+        for target in targets {
+            let result = match self.fetchers.get(&target.host) {
+                Ok(fetcher) => self.fetch_with_credential(fetcher, &target).await,
+                Err(e) => Err(e),
+            };
+            let event = match result {
+                Ok(mut pr) => {
+                    self.estimate(&target, &mut pr, now).await;
+                    StatusEvent {
+                        target: target.clone(),
+                        archived: self.should_archive(&pr, now),
+                        pr: Some(pr),
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, ?target, "error fetching pr status");
+                    StatusEvent {
+                        target: target.clone(),
+                        pr: None,
+                        error: Some(e),
+                        archived: false,
+                    }
+                }
+            };
+
+            let mut watched = self.watched.lock().await;
+            // the PR may have been unwatched while we were polling
+            let Some(entry) = watched.get_mut(&target) else {
+                continue;
+            };
+            let changed = entry.last.as_ref() != Some(&event);
+            if changed {
+                entry.last = Some(event.clone());
+                events.push(event.clone());
+            }
+            let archive = event.archived && event.pr.is_some();
+            if let (true, Some(pr)) = (archive, &event.pr) {
+                tracing::debug!(?target, "archiving pr");
+                watched.remove(&target);
+                self.archived.lock().await.insert(
+                    target.clone(),
+                    ArchivedPr {
+                        target: target.clone(),
+                        pr: pr.clone(),
+                        archived_at: now,
+                    },
+                );
+            }
+            drop(watched);
+
+            let result = self
+                .in_background(move |history| {
+                    if changed {
+                        history.record(&event, now)?;
+                    }
+                    if let (true, Some(pr)) = (archive, &event.pr) {
+                        history.archive(&event.target, pr, now)?;
+                    }
+                    Ok(())
+                })
+                .await;
+            if let Err(e) = result {
+                tracing::warn!(error = ?e, ?target, "error recording pr history");
+            }
+        }
+        events
+    }
+
+    /// Run blocking database work on a thread of its own, off the async runtime
+    async fn in_background<T: Send + 'static>(
+        &self,
+        work: impl FnOnce(&History) -> eyre::Result<T> + Send + 'static,
+    ) -> eyre::Result<T> {
+        let history = Arc::clone(&self.history);
+        tokio::task::spawn_blocking(move || work(&history)).await?
+    }
+
+    /// Estimate how far along the PR's runs are from the durations of past runs
+    async fn estimate(&self, target: &WatchTarget, pr: &mut Pr, now: u64) {
+        let by_step = self.progress_strategy == ProgressStrategy::Duration;
+        let workflow_ids: Vec<u64> = pr.workflows.iter().map(|w| w.workflow_id).collect();
+        let t = target.clone();
+        let mut durations = self
+            .in_background(move |history| {
+                workflow_ids
+                    .into_iter()
+                    .map(|id| {
+                        let jobs = history.job_durations(&t.host, &t.owner, &t.repo, id)?;
+                        let steps = match by_step {
+                            true => history.step_durations(&t.host, &t.owner, &t.repo, id)?,
+                            false => StepDurations::default(),
+                        };
+                        Ok((id, (jobs, steps)))
+                    })
+                    .collect::<eyre::Result<WorkflowDurations>>()
+            })
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = ?e, ?target, "error reading past durations");
+                Default::default()
+            });
+        // workflows never seen running locally fall back to their recent runs on GitHub
+        for workflow in &pr.workflows {
+            let seen = durations
+                .get(&workflow.workflow_id)
+                .is_some_and(|(jobs, _)| !jobs.is_empty());
+            if seen || !workflow.status.is_running() {
+                continue;
+            }
+            if let Some(from_api) = self.api_durations(target, workflow.workflow_id).await {
+                durations.insert(workflow.workflow_id, from_api);
+            }
+        }
+        estimate::apply_durations(pr, &durations, self.progress_strategy, now as i64);
+    }
+
+    /// Durations of a workflow's recent successful runs from the API, fetched once per workflow
+    async fn api_durations(
+        &self,
+        target: &WatchTarget,
+        workflow_id: u64,
+    ) -> Option<(JobDurations, StepDurations)> {
+        let key = (
+            target.host.clone(),
+            target.owner.clone(),
+            target.repo.clone(),
+            workflow_id,
+        );
+        if let Some(durations) = self.api_durations.lock().await.get(&key) {
+            return Some(durations.clone());
+        }
+        let fetcher = self.fetchers.get(&target.host).ok()?;
+        let resolved = self
+            .tokens
+            .resolve(&target.host, Some(&target.owner))
+            .await?;
+        let result = fetcher
+            .past_durations(&target.owner, &target.repo, workflow_id, &resolved.token)
+            .await;
+        match result {
+            Ok(durations) => {
+                self.api_durations
+                    .lock()
+                    .await
+                    .insert(key, durations.clone());
+                Some(durations)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, ?target, "error fetching past run durations");
+                None
+            }
+        }
+    }
+
+    fn should_archive(&self, pr: &Pr, now: u64) -> bool {
+        if pr.state == PrState::Open {
+            return false;
+        }
+        // GitHub always reports when a PR was closed, archive straight away if it did not
+        let closed_at = pr.closed_at.unwrap_or_default().max(0) as u64;
+        now >= closed_at + self.archive_after.as_secs()
+    }
+
+    /// Fetch with the credential routed to the target's owner, naming it in any error
+    ///
+    /// The token is looked up on every poll so logging in again takes effect straight away.
+    async fn fetch_with_credential(
+        &self,
+        fetcher: &Fetcher,
+        target: &WatchTarget,
+    ) -> error::Result<Pr> {
+        let Some(resolved) = self.tokens.resolve(&target.host, Some(&target.owner)).await else {
+            return Err(Error::NoToken {
+                host: target.host.clone(),
+                owner: Some(target.owner.clone()),
+            });
+        };
+        let result = Self::fetch(fetcher, target, &resolved.token)
+            .await
+            .map_err(|e| e.for_credential(&resolved.credential));
+        self.check_expiry(fetcher, &target.host, &resolved).await;
+        result
+    }
+
+    /// Queue a warning the first time a token is seen to expire soon
+    async fn check_expiry(&self, fetcher: &Fetcher, host: &str, resolved: &ResolvedToken) {
+        let Some(expires_at) = fetcher.token_expiry(&resolved.token) else {
+            return;
+        };
+        if !token::expires_soon(expires_at, chrono::Utc::now().timestamp()) {
+            return;
+        }
+        let key = rate_limit::token_key(&resolved.token);
+        if self.warned_tokens.lock().await.insert(key) {
+            tracing::info!(%host, credential = %resolved.credential, %expires_at, "token expires soon");
+            self.expiring_tokens.lock().await.push(ExpiringToken {
+                host: host.to_string(),
+                credential: resolved.credential.clone(),
+                expires_at,
+            });
+        }
+    }
+
+    /// Tokens found to expire soon since the last call, each reported only once
+    pub async fn take_expiring_tokens(&self) -> Vec<ExpiringToken> {
+        std::mem::take(&mut *self.expiring_tokens.lock().await)
+    }
+
+    async fn fetch(fetcher: &Fetcher, target: &WatchTarget, token: &str) -> error::Result<Pr> {
+        match target.workflow_id {
+            Some(workflow_id) => {
+                fetcher
+                    .fetch(
+                        token,
+                        &target.owner,
+                        &target.repo,
+                        workflow_id,
+                        target.pr_number,
+                    )
+                    .await
+            }
+            None => {
+                fetcher
+                    .fetch_pr_status(token, &target.owner, &target.repo, target.pr_number)
+                    .await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    use crate::{
+        config::{AppConfig, DiscoveryConfig, HostConfig, DEFAULT_HOST},
+        error::Error,
+        fetcher::{Fetchers, PrState},
+        history::History,
+        token_store::TokenStore,
+    };
+
+    use super::{ExpiringToken, WatchTarget, Watcher};
+
+    #[tokio::test]
+    async fn only_reports_changes() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(404);
+            })
+            .await;
+
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                api_base_url: server.base_url(),
+                ..HostConfig::github()
+            }],
+            ..Default::default()
+        };
+        let fetchers = Arc::new(Fetchers::from_config(Arc::new(config)));
+        let tokens = Arc::new(TokenStore::in_memory());
+        tokens.set(DEFAULT_HOST, "abc123").unwrap();
+        let watcher = Watcher::new(fetchers, tokens, Duration::from_secs(10));
+        let target = WatchTarget {
+            host: DEFAULT_HOST.into(),
+            owner: "owner".into(),
+            repo: "repo".into(),
+            pr_number: 1,
+            workflow_id: None,
+        };
+        watcher.watch(target.clone()).await;
+
+        let events = watcher.poll().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].target, target);
+        assert_eq!(
+            events[0].error,
+            Some(Error::NotFound {
+                resource: "/repos/owner/repo/pulls/1".into(),
+                credential: Some(DEFAULT_HOST.into()),
+            })
+        );
+
+        // nothing changed so nothing is reported
+        assert!(watcher.poll().await.is_empty());
+        assert_eq!(watcher.statuses().await.len(), 1);
+
+        watcher.unwatch(&target).await;
+        assert!(watcher.statuses().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn warns_once_about_expiring_tokens() {
+        let server = MockServer::start_async().await;
+        let expires_at = chrono::Utc::now() + chrono::Duration::days(2);
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(404).header(
+                    "github-authentication-token-expiration",
+                    expires_at.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                );
+            })
+            .await;
+
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                api_base_url: server.base_url(),
+                ..HostConfig::github()
+            }],
+            ..Default::default()
+        };
+        let fetchers = Arc::new(Fetchers::from_config(Arc::new(config)));
+        let tokens = Arc::new(TokenStore::in_memory());
+        tokens.set(DEFAULT_HOST, "abc123").unwrap();
+        let watcher = Watcher::new(fetchers, tokens, Duration::from_secs(10));
+        watcher
+            .watch(WatchTarget {
+                host: DEFAULT_HOST.into(),
+                owner: "owner".into(),
+                repo: "repo".into(),
+                pr_number: 1,
+                workflow_id: None,
+            })
+            .await;
+
+        watcher.poll().await;
+        assert_eq!(
+            watcher.take_expiring_tokens().await,
+            vec![ExpiringToken {
+                host: DEFAULT_HOST.into(),
+                credential: DEFAULT_HOST.into(),
+                expires_at: expires_at.timestamp(),
+            }]
+        );
+
+        watcher.poll().await;
+        assert!(watcher.take_expiring_tokens().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn discovers_open_prs() {
+        let server = MockServer::start_async().await;
+        let search = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path("/search/issues")
+                    .query_param("q", "is:pr is:open author:@me org:my-org");
+                then.status(200).json_body(json!({ "items": [
+                    { "number": 1, "repository_url": "https://api.github.com/repos/my-org/app" },
+                    { "number": 2, "repository_url": "https://api.github.com/repos/my-org/lib" },
+                ] }));
+            })
+            .await;
+
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                api_base_url: server.base_url(),
+                ..HostConfig::github()
+            }],
+            ..Default::default()
+        };
+        let fetchers = Arc::new(Fetchers::from_config(Arc::new(config)));
+        let tokens = Arc::new(TokenStore::in_memory());
+        tokens.set(DEFAULT_HOST, "abc123").unwrap();
+        let watcher = Watcher::new(fetchers, tokens, Duration::from_secs(10)).with_discovery(
+            DiscoveryConfig {
+                enabled: true,
+                orgs: vec!["my-org".into()],
+                ..Default::default()
+            },
+        );
+        let target = |repo: &str, pr_number| WatchTarget {
+            host: DEFAULT_HOST.into(),
+            owner: "my-org".into(),
+            repo: repo.into(),
+            pr_number,
+            workflow_id: None,
+        };
+        // already watched by the user, for a single workflow
+        let manual = WatchTarget {
+            workflow_id: Some(10),
+            ..target("app", 1)
+        };
+        watcher.watch(manual.clone()).await;
+
+        assert!(watcher.discover().await);
+        assert_eq!(watcher.discovered().await, vec![target("lib", 2)]);
+        assert!(!watcher.discover().await);
+
+        // closed PRs are archived by polling rather than forgotten
+        search.delete_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/search/issues");
+                then.status(200).json_body(json!({ "items": [] }));
+            })
+            .await;
+        assert!(!watcher.discover().await);
+        assert_eq!(watcher.discovered().await, vec![target("lib", 2)]);
+        assert!(watcher.watched.lock().await.contains_key(&manual));
+    }
+
+    #[tokio::test]
+    async fn removed_discovered_prs_are_not_rediscovered() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/search/issues");
+                then.status(200).json_body(json!({ "items": [
+                    { "number": 2, "repository_url": "https://api.github.com/repos/my-org/lib" },
+                ] }));
+            })
+            .await;
+
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                api_base_url: server.base_url(),
+                ..HostConfig::github()
+            }],
+            ..Default::default()
+        };
+        let fetchers = Arc::new(Fetchers::from_config(Arc::new(config)));
+        let tokens = Arc::new(TokenStore::in_memory());
+        tokens.set(DEFAULT_HOST, "abc123").unwrap();
+        let history = Arc::new(History::in_memory());
+        let new_watcher = || {
+            Watcher::new(
+                Arc::clone(&fetchers),
+                Arc::clone(&tokens),
+                Duration::from_secs(10),
+            )
+            .with_discovery(DiscoveryConfig {
+                enabled: true,
+                ..Default::default()
+            })
+            .with_history(Arc::clone(&history))
+        };
+        let target = WatchTarget {
+            host: DEFAULT_HOST.into(),
+            owner: "my-org".into(),
+            repo: "lib".into(),
+            pr_number: 2,
+            workflow_id: None,
+        };
+
+        let watcher = new_watcher();
+        assert!(watcher.discover().await);
+        watcher.unwatch(&target).await;
+        assert!(!watcher.discover().await);
+        assert!(watcher.discovered().await.is_empty());
+
+        // still dismissed after a restart
+        let watcher = new_watcher();
+        assert!(!watcher.discover().await);
+
+        // until the user watches it themselves
+        watcher.watch(target).await;
+        assert!(watcher.dismissed.lock().await.is_empty());
+        assert!(history.dismissed_prs().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn archives_closed_prs_after_grace_period() {
+        let server = MockServer::start_async().await;
+        let now = chrono::Utc::now();
+        let pr = |number: u64, merged_at: chrono::DateTime<chrono::Utc>| {
+            json!({
+                "title": "My PR",
+                "head": { "ref": "my-branch", "sha": "abc", "repo": null },
+                "body": null,
+                "html_url": format!("https://github.com/owner/repo/pull/{number}"),
+                "state": "closed",
+                "merged": true,
+                "merged_at": merged_at.to_rfc3339(),
+                "closed_at": merged_at.to_rfc3339(),
+            })
+        };
+        let old = server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/1");
+                then.status(200)
+                    .json_body(pr(1, now - chrono::Duration::hours(1)));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/pulls/2");
+                then.status(200).json_body(pr(2, now));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method(GET).path("/repos/owner/repo/actions/runs");
+                then.status(200)
+                    .json_body(json!({ "workflow_runs": [], "total_count": 0 }));
+            })
+            .await;
+
+        let config = AppConfig {
+            hosts: vec![HostConfig {
+                api_base_url: server.base_url(),
+                ..HostConfig::github()
+            }],
+            ..Default::default()
+        };
+        let fetchers = Arc::new(Fetchers::from_config(Arc::new(config)));
+        let tokens = Arc::new(TokenStore::in_memory());
+        tokens.set(DEFAULT_HOST, "abc123").unwrap();
+        let watcher = Watcher::new(fetchers, tokens, Duration::from_secs(10))
+            .with_archive_after(Duration::from_secs(600));
+        let target = |pr_number| WatchTarget {
+            host: DEFAULT_HOST.into(),
+            owner: "owner".into(),
+            repo: "repo".into(),
+            pr_number,
+            workflow_id: None,
+        };
+        watcher.watch(target(1)).await;
+        watcher.watch(target(2)).await;
+
+        let events = watcher.poll().await;
+        assert_eq!(events.len(), 2);
+        let archived: Vec<_> = events.iter().filter(|e| e.archived).collect();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].target, target(1));
+        assert_eq!(archived[0].pr.as_ref().unwrap().state, PrState::Merged);
+
+        // the archived PR is no longer polled
+        watcher.poll().await;
+        assert_eq!(old.hits_async().await, 1);
+        let archived = watcher.archived_prs().await;
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].target, target(1));
+        assert_eq!(watcher.statuses().await.len(), 1);
+
+        // watching it again takes it back out of the archive
+        watcher.watch(target(1)).await;
+        assert!(watcher.archived_prs().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn unknown_host_is_an_error() {
+        let fetchers = Arc::new(Fetchers::from_config(Default::default()));
+        let tokens = Arc::new(TokenStore::in_memory());
+        tokens.set(DEFAULT_HOST, "abc123").unwrap();
+        let watcher = Watcher::new(fetchers, tokens, Duration::from_secs(10));
+        let target = WatchTarget {
+            host: "github.example.com".into(),
+            owner: "owner".into(),
+            repo: "repo".into(),
+            pr_number: 1,
+            workflow_id: None,
+        };
+        watcher.watch(target).await;
+
+        let events = watcher.poll().await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].error,
+            Some(Error::UnknownHost {
+                host: "github.example.com".into()
+            })
+        );
+    }
+}
@@ -0,0 +1,197 @@
+import { useForm } from "react-hook-form";
+import { zodResolver } from "@hookform/resolvers/zod";
+import { z } from "zod";
+import { useQuery, useQueryClient } from "@tanstack/react-query";
+import { invoke } from "@tauri-apps/api/core";
+import { X } from "lucide-react";
+import {
+  Form,
+  FormControl,
+  FormField,
+  FormItem,
+  FormLabel,
+  FormMessage,
+} from "./ui/form";
+import { Button } from "./ui/button";
+import { Input } from "./ui/input";
+import {
+  Select,
+  SelectItem,
+  SelectContent,
+  SelectTrigger,
+  SelectValue,
+} from "./ui/select";
+import {
+  ApiError,
+  CredentialInfo,
+  DEFAULT_HOST,
+  errorMessage,
+  HostConfig,
+} from "@/types";
+
+const formSchema = z.object({
+  name: z.string().min(1),
+  host: z.string().min(1),
+  // comma separated, empty to use the token for every owner on the host
+  owners: z.string(),
+  token: z.string().min(1),
+});
+
+// Named tokens, each used for the PRs of the owners it lists
+export function CredentialsForm() {
+  const form = useForm<z.infer<typeof formSchema>>({
+    resolver: zodResolver(formSchema),
+    defaultValues: {
+      name: "",
+      host: DEFAULT_HOST,
+      owners: "",
+      token: "",
+    },
+  });
+
+  const queryClient = useQueryClient();
+
+  const { data: hosts } = useQuery<HostConfig[]>({
+    queryKey: ["hosts"],
+    queryFn: () => invoke("list_hosts"),
+  });
+
+  const { data: credentials } = useQuery<CredentialInfo[]>({
+    queryKey: ["credentials"],
+    queryFn: () => invoke("list_credentials"),
+  });
+
+  async function remove(name: string) {
+    await invoke("remove_credential", { name });
+    await queryClient.invalidateQueries({ queryKey: ["credentials"] });
+    await queryClient.invalidateQueries({ queryKey: ["has-token"] });
+  }
+
+  async function onSubmit(values: z.infer<typeof formSchema>) {
+    const owners = values.owners
+      .split(",")
+      .map((owner) => owner.trim())
+      .filter((owner) => owner !== "");
+    try {
+      await invoke("add_credential", {
+        name: values.name,
+        host: values.host,
+        owners,
+        token: values.token,
+      });
+    } catch (e) {
+      form.setError("token", { message: errorMessage(e as ApiError) });
+      return;
+    }
+    form.reset();
+    await queryClient.invalidateQueries({ queryKey: ["credentials"] });
+  }
+
+  return (
+    <div className="flex flex-col gap-4">
+      <ul className="flex flex-col gap-1">
+        {(credentials || []).map((credential) => (
+          <li
+            key={credential.name}
+            className="flex items-center justify-between gap-2"
+          >
+            <span>
+              {credential.name}{" "}
+              <span className="text-muted-foreground">
+                {credential.owners.length > 0
+                  ? credential.owners.join(", ")
+                  : credential.host}
+                {credential.gh_cli && " (gh CLI)"}
+              </span>
+            </span>
+            <Button
+              variant="ghost"
+              size="icon"
+              onClick={() => remove(credential.name)}
+            >
+              <X />
+            </Button>
+          </li>
+        ))}
+      </ul>
+      <Form {...form}>
+        <form
+          onSubmit={form.handleSubmit(onSubmit)}
+          className="flex flex-col gap-2"
+        >
+          <FormField
+            control={form.control}
+            name="name"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Name</FormLabel>
+                <FormControl>
+                  <Input placeholder="work" {...field} />
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+          {hosts && hosts.length > 1 && (
+            <FormField
+              control={form.control}
+              name="host"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Host</FormLabel>
+                  <Select
+                    onValueChange={field.onChange}
+                    defaultValue={field.value}
+                  >
+                    <FormControl>
+                      <SelectTrigger>
+                        <SelectValue placeholder="Select a host" />
+                      </SelectTrigger>
+                    </FormControl>
+                    <SelectContent>
+                      {hosts.map((host) => (
+                        <SelectItem key={host.name} value={host.name}>
+                          {host.name}
+                        </SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
+          <FormField
+            control={form.control}
+            name="owners"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Use for owners</FormLabel>
+                <FormControl>
+                  <Input placeholder="my-org, other-org" {...field} />
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+          <FormField
+            control={form.control}
+            name="token"
+            render={({ field }) => (
+              <FormItem>
+                <FormLabel>Token</FormLabel>
+                <FormControl>
+                  <Input placeholder="GitHub personal access token" {...field} />
+                </FormControl>
+                <FormMessage />
+              </FormItem>
+            )}
+          />
+          <Button variant="secondary" type="submit">
+            Add token
+          </Button>
+        </form>
+      </Form>
+    </div>
+  );
+}
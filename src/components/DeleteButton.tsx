@@ -11,10 +11,11 @@ import {
 } from "./ui/alert-dialog";
 import { Trash } from "lucide-react";
 import { Button } from "./ui/button";
+import { Pr } from "@/types";
 
 type DeleteButtonProps = {
-  pr: number;
-  removePr: (prNumber: number) => void;
+  pr: Pr;
+  removePr: (pr: Pr) => void;
 };
 
 export function DeleteButton({ pr, removePr }: DeleteButtonProps) {
@@ -4,6 +4,8 @@ import { Button } from "./ui/button";
 import { Popover, PopoverContent, PopoverTrigger } from "./ui/popover";
 import { ModeToggle } from "./ui/ThemeModeToggle";
 import { LogoutButton } from "./LogoutButton";
+import { CredentialsForm } from "./CredentialsForm";
+import { KeyRound } from "lucide-react";
 
 type HeaderProps = {
   addPr: (pr: Pr) => void;
@@ -24,6 +26,16 @@ export function Header({ addPr }: HeaderProps) {
             <InputForm addPr={addPr} />
           </PopoverContent>
         </Popover>
+        <Popover>
+          <PopoverTrigger asChild>
+            <Button variant="outline" size="icon">
+              <KeyRound />
+            </Button>
+          </PopoverTrigger>
+          <PopoverContent>
+            <CredentialsForm />
+          </PopoverContent>
+        </Popover>
         <ModeToggle />
         <LogoutButton />
       </div>
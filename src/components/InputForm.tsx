@@ -1,3 +1,4 @@
+import { useState } from "react";
 import { useForm } from "react-hook-form";
 import { zodResolver } from "@hookform/resolvers/zod";
 import { z } from "zod";
@@ -11,11 +12,16 @@ import {
 } from "./ui/form";
 import { Button } from "./ui/button";
 import { Input } from "./ui/input";
-import { Pr } from "@/types";
+import {
+  ApiError,
+  DEFAULT_HOST,
+  errorMessage,
+  HostConfig,
+  ParsedReference,
+  Pr,
+} from "@/types";
 import { useQuery } from "@tanstack/react-query";
 import { invoke } from "@tauri-apps/api/core";
-import { useContext } from "react";
-import { StorageContext } from "@/lib/storage";
 import {
   Select,
   SelectItem,
@@ -25,6 +31,7 @@ import {
 } from "./ui/select";
 
 const formSchema = z.object({
+  host: z.string().min(1),
   owner: z.string().min(1).max(50, {
     message: "Owner must be less than 50 characters",
   }),
@@ -46,11 +53,10 @@ type Workflow = {
 };
 
 export function InputForm(props: InputFormProps) {
-  const storage = useContext(StorageContext);
-
   const form = useForm<z.infer<typeof formSchema>>({
     resolver: zodResolver(formSchema),
     defaultValues: {
+      host: DEFAULT_HOST,
       owner: "",
       repo: "",
       workflow: 0,
@@ -58,18 +64,23 @@ export function InputForm(props: InputFormProps) {
     },
   });
 
+  const { data: hosts } = useQuery<HostConfig[]>({
+    queryKey: ["hosts"],
+    queryFn: () => invoke("list_hosts"),
+  });
+
   const w = form.watch();
   const { data: workflows } = useQuery<Workflow[]>({
-    queryKey: ["workflows", w.owner, w.repo],
+    queryKey: ["workflows", w.host, w.owner, w.repo],
     queryFn: async ({ signal }) => {
       // sleep
       await new Promise((resolve) => setTimeout(resolve, 500));
 
       if (!signal?.aborted) {
         const workflows: Workflow[] = await invoke("fetch_workflows_for_repo", {
+          host: w.host,
           owner: w.owner,
           repo: w.repo,
-          token: storage.getToken(),
         });
 
         return workflows;
@@ -80,12 +91,39 @@ export function InputForm(props: InputFormProps) {
     enabled: w.owner !== "" && w.repo !== "",
   });
 
+  const [reference, setReference] = useState("");
+  const [referenceError, setReferenceError] = useState<string | null>(null);
+
+  async function addFromReference() {
+    let parsed: ParsedReference;
+    try {
+      parsed = await invoke<ParsedReference>("parse_github_reference", {
+        input: reference,
+      });
+    } catch (e) {
+      setReferenceError(errorMessage(e as ApiError));
+      return;
+    }
+    const { target } = parsed;
+    props.addPr({
+      status: { kind: "unknown" },
+      host: target.host,
+      number: target.pr_number,
+      owner: target.owner,
+      workflowId: target.workflow_id,
+      repo: target.repo,
+    });
+    setReference("");
+    setReferenceError(null);
+  }
+
   function onSubmit(values: z.infer<typeof formSchema>) {
     props.addPr({
       status: { kind: "unknown" },
+      host: values.host,
       number: values.pr,
       owner: values.owner,
-      workflowId: values.workflow,
+      workflowId: values.workflow === 0 ? null : values.workflow,
       repo: values.repo,
     });
     form.resetField("pr");
@@ -93,8 +131,64 @@ export function InputForm(props: InputFormProps) {
 
   return (
     <Form {...form}>
+      <div className="flex flex-col gap-2 mb-8">
+        <div className="flex gap-2">
+          <Input
+            placeholder="Paste a PR, run or branch URL, or owner/repo#123"
+            value={reference}
+            onChange={(e) => setReference(e.target.value)}
+            onKeyDown={(e) => {
+              if (e.key === "Enter") {
+                e.preventDefault();
+                addFromReference();
+              }
+            }}
+          />
+          <Button
+            variant="secondary"
+            disabled={reference.trim() === ""}
+            onClick={addFromReference}
+          >
+            Add
+          </Button>
+        </div>
+        {referenceError && (
+          <p className="text-sm font-medium text-destructive">
+            {referenceError}
+          </p>
+        )}
+      </div>
       <form onSubmit={form.handleSubmit(onSubmit)} className="flex flex-col gap-8">
         <div className="flex flex-col gap-2">
+          {hosts && hosts.length > 1 && (
+            <FormField
+              control={form.control}
+              name="host"
+              render={({ field }) => (
+                <FormItem>
+                  <FormLabel>Host</FormLabel>
+                  <Select
+                    onValueChange={field.onChange}
+                    defaultValue={field.value}
+                  >
+                    <FormControl>
+                      <SelectTrigger>
+                        <SelectValue placeholder="Select a host" />
+                      </SelectTrigger>
+                    </FormControl>
+                    <SelectContent>
+                      {hosts.map((host) => (
+                        <SelectItem key={host.name} value={host.name}>
+                          {host.name}
+                        </SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
+                  <FormMessage />
+                </FormItem>
+              )}
+            />
+          )}
           <FormField
             control={form.control}
             name="owner"
@@ -151,6 +245,7 @@ export function InputForm(props: InputFormProps) {
                     </SelectTrigger>
                   </FormControl>
                   <SelectContent>
+                    <SelectItem value="0">All workflows</SelectItem>
                     {(workflows || []).map((workflow) => {
                       return (
                         <SelectItem value={workflow.id.toString()}>
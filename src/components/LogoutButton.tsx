@@ -3,14 +3,19 @@ import { MouseEventHandler, useContext } from "react";
 import { Button } from "./ui/button";
 import { LogOut } from "lucide-react";
 import { useNavigate } from "react-router-dom";
+import { invoke } from "@tauri-apps/api/core";
+import { useQueryClient } from "@tanstack/react-query";
 
 export function LogoutButton() {
   const storage = useContext(StorageContext);
   const naviagte = useNavigate();
+  const queryClient = useQueryClient();
 
-  const logout: MouseEventHandler<HTMLButtonElement> = (e) => {
+  const logout: MouseEventHandler<HTMLButtonElement> = async (e) => {
     e.preventDefault();
+    await invoke("clear_token");
     storage.reset();
+    await queryClient.invalidateQueries({ queryKey: ["has-token"] });
     naviagte("/auth");
   };
 
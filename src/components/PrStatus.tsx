@@ -1,6 +1,10 @@
-import { useQuery } from "@tanstack/react-query";
-import { Pr, RawStatus, Status, statusFromRaw, StatusPayload } from "../types";
-import { invoke } from "@tauri-apps/api/core";
+import {
+  errorMessage,
+  errorTitle,
+  Pr,
+  WatchedStatus,
+  WorkflowStatus,
+} from "../types";
 import Markdown from "react-markdown";
 import {
   Card,
@@ -9,9 +13,9 @@ import {
   CardHeader,
   CardTitle,
 } from "./ui/card";
-import { ProgressReport } from "./ProgressReport";
+import { elapsed, ProgressReport, useNow } from "./ProgressReport";
 import { DeleteButton } from "./DeleteButton";
-import { ReactElement, useContext, useState } from "react";
+import { ReactElement } from "react";
 import {
   Collapsible,
   CollapsibleContent,
@@ -22,73 +26,44 @@ import {
   ClipboardCheck,
   GitPullRequestArrow,
 } from "lucide-react";
-import { StorageContext } from "@/lib/storage";
 import { Tooltip, TooltipContent, TooltipTrigger } from "./ui/tooltip";
 
-type PrStatusResponse = {
-  status: RawStatus;
-  title: string;
-  description: string;
-  num_steps: number;
-  num_complete_steps: number;
-  pr_url: string;
-  run_url: string;
-};
-
 type PrStatusProps = {
   pr: Pr;
-  removePr: (prNumber: number) => void;
+  // undefined until the watcher has polled the PR for the first time
+  status: WatchedStatus | undefined;
+  removePr: (pr: Pr) => void;
 };
 
-export function PrStatus({ pr, removePr }: PrStatusProps) {
-  const [prevStatus, setPrevStatus] = useState<Status | null>(null);
-  const storage = useContext(StorageContext);
-
-  const { data, isLoading, error } = useQuery<StatusPayload>({
-    queryKey: ["pr", pr.number],
-    queryFn: async () => {
-      const response = await invoke<PrStatusResponse>("fetch_status", {
-        owner: pr.owner,
-        repo: pr.repo,
-        prNumber: pr.number,
-        workflowId: pr.workflowId,
-        token: storage.getToken(),
-      });
+export function PrStatus({ pr, status, removePr }: PrStatusProps) {
+  const data = status?.data;
+  const error = status?.error;
 
-      return {
-        owner: pr.owner,
-        repo: pr.repo,
-        status: statusFromRaw(response.status),
-        title: response.title,
-        number: pr.number,
-        description: response.description,
-        numSteps: response.num_steps,
-        numCompleteSteps: response.num_complete_steps,
-        prUrl: response.pr_url,
-        runUrl: response.run_url,
-      };
-    },
-    refetchInterval: 10000,
-  });
-
-  if (error)
+  if (error) {
+    // these resolve themselves, so are not shown as failures
+    const transient =
+      error.kind === "rate_limited" ||
+      (error.kind === "no_runs" && error.previous_runs);
     return (
-      <Card className="border border-red-300">
+      <Card
+        className={`border ${transient ? "border-yellow-600" : "border-red-300"}`}
+      >
         <CardHeader>
           <CardTitle>
             <div className="flex justify-between">
               <div>
-                <p>Error</p>
+                <p>{errorTitle(error)}</p>
               </div>
-              <DeleteButton pr={pr.number} removePr={removePr} />
+              <DeleteButton pr={pr} removePr={removePr} />
             </div>
           </CardTitle>
         </CardHeader>
-        <CardContent>{error.toString()}</CardContent>
+        <CardContent>{errorMessage(error)}</CardContent>
       </Card>
     );
+  }
 
-  if (isLoading || !data) {
+  if (!data) {
     return (
       <Card className="border border-yellow-600 animate-pulse">
         <CardHeader>
@@ -97,7 +72,7 @@ export function PrStatus({ pr, removePr }: PrStatusProps) {
               <div>
                 <p>Loading...</p>
               </div>
-              <DeleteButton pr={pr.number} removePr={removePr} />
+              <DeleteButton pr={pr} removePr={removePr} />
             </div>
           </CardTitle>
         </CardHeader>
@@ -106,11 +81,6 @@ export function PrStatus({ pr, removePr }: PrStatusProps) {
     );
   }
 
-  if (data.status !== prevStatus) {
-    setPrevStatus(data.status);
-    createNotification(data);
-  }
-
   let borderColor = "";
   switch (data.status.kind) {
     case "succeeded":
@@ -120,11 +90,19 @@ export function PrStatus({ pr, removePr }: PrStatusProps) {
       borderColor = "animate-pulse";
       break;
     case "queued":
+    case "waiting":
       borderColor = "animate-pulse";
       break;
     case "failed":
+    case "timed-out":
+    case "startup-failure":
       borderColor = "border border-red-500";
       break;
+    case "cancelled":
+    case "action-required":
+    case "stale":
+      borderColor = "border border-yellow-600";
+      break;
   }
 
   return (
@@ -142,15 +120,20 @@ export function PrStatus({ pr, removePr }: PrStatusProps) {
                 </IconLink>
               </span>
               <span className="text-xl">{data.title}</span>
+              {data.state !== "open" && (
+                <span className="text-sm text-muted-foreground">
+                  {data.state}
+                </span>
+              )}
             </p>
-            <DeleteButton pr={pr.number} removePr={removePr} />
+            <DeleteButton pr={pr} removePr={removePr} />
           </div>
         </CardTitle>
         <CardDescription>
           <Collapsible>
             <CollapsibleTrigger className="flex items-center gap-2">
               <p>
-                {pr.owner}/{pr.repo} (#{pr.number})
+                {pr.owner}/{pr.repo} (#{pr.number}) {data.branch}
               </p>
               <ChevronsUpDown />
             </CollapsibleTrigger>
@@ -165,12 +148,42 @@ export function PrStatus({ pr, removePr }: PrStatusProps) {
           status={data.status}
           numCompleteSteps={data.numCompleteSteps}
           numSteps={data.numSteps}
+          estimatedProgress={data.estimatedProgress}
+          estimatedCompletion={data.estimatedCompletion}
+          timing={data.timing}
         />
+        {data.workflows.length > 1 && (
+          <WorkflowBreakdown workflows={data.workflows} />
+        )}
       </CardContent>
     </Card>
   );
 }
 
+type WorkflowBreakdownProps = {
+  workflows: WorkflowStatus[];
+};
+
+function WorkflowBreakdown({ workflows }: WorkflowBreakdownProps) {
+  const now = useNow();
+  return (
+    <ul className="flex flex-col gap-1 pt-4 text-sm text-muted-foreground">
+      {workflows.map((workflow) => (
+        <li key={workflow.workflowId} className="flex justify-between">
+          <a href={workflow.runUrl} target="_blank">
+            {workflow.name}
+          </a>
+          <span>
+            {workflow.status.kind}
+            {elapsed(workflow.timing, now) &&
+              `, ${elapsed(workflow.timing, now)}`}
+          </span>
+        </li>
+      ))}
+    </ul>
+  );
+}
+
 type IconLinkProps = {
   url: string;
   tooltip: string;
@@ -193,23 +206,3 @@ function IconLink(props: IconLinkProps) {
     </Tooltip>
   );
 }
-
-function createNotification(pr: StatusPayload): Notification | null {
-  let title;
-  const body = `${pr.title} (#${pr.number})`;
-
-  switch (pr.status.kind) {
-    case "succeeded":
-      title = `${pr.owner}/${pr.repo}#${pr.number} succeeded!`;
-      break;
-    case "failed":
-      title = `${pr.owner}/${pr.repo}#${pr.number} failed`;
-      break;
-    case "queued":
-    case "in-progress":
-    case "unknown":
-      return null;
-  }
-
-  return new Notification(title, { body });
-}
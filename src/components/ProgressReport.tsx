@@ -1,21 +1,83 @@
-import { Status } from "@/types";
+import { useEffect, useState } from "react";
+import { Status, Timing } from "@/types";
 import { Progress } from "./ui/progress";
 
 type ProgressReportProps = {
   status: Status;
   numSteps: number;
   numCompleteSteps: number;
+  estimatedProgress: number | null;
+  estimatedCompletion: number | null;
+  timing: Timing;
+};
+
+// the current unix time (in seconds), updated every few seconds so times relative to it stay
+// current between status updates
+export const useNow = (): number => {
+  const [now, setNow] = useState(() => Date.now() / 1000);
+  useEffect(() => {
+    const id = setInterval(() => setNow(Date.now() / 1000), 5000);
+    return () => clearInterval(id);
+  }, []);
+  return now;
+};
+
+// e.g. "3m left", rounding up so a run is never shown as having no time left
+const timeLeft = (completion: number, now: number): string => {
+  const minutes = Math.ceil((completion - now) / 60);
+  if (minutes <= 1) {
+    return "almost done";
+  }
+  if (minutes < 60) {
+    return `${minutes}m left`;
+  }
+  return `${Math.floor(minutes / 60)}h ${minutes % 60}m left`;
+};
+
+// e.g. "45s", "12m" or "1h 5m"
+const duration = (secs: number): string => {
+  if (secs < 60) {
+    return `${secs}s`;
+  }
+  const minutes = Math.floor(secs / 60);
+  if (minutes < 60) {
+    return `${minutes}m`;
+  }
+  return `${Math.floor(minutes / 60)}h ${minutes % 60}m`;
+};
+
+// e.g. "queued for 2m", "running for 12m" or "took 12m, queued 2m"
+export const elapsed = (timing: Timing, now: number): string | null => {
+  const since = (from: number, to: number) =>
+    duration(Math.max(0, Math.floor(to - from)));
+  if (timing.startedAt === null) {
+    return timing.createdAt === null || timing.completedAt !== null
+      ? null
+      : `queued for ${since(timing.createdAt, now)}`;
+  }
+  const ran =
+    timing.completedAt === null
+      ? `running for ${since(timing.startedAt, now)}`
+      : `took ${since(timing.startedAt, timing.completedAt)}`;
+  return timing.queuedSecs
+    ? `${ran}, queued ${duration(timing.queuedSecs)}`
+    : ran;
 };
 
 export function ProgressReport({
   status,
   numSteps,
   numCompleteSteps,
+  estimatedProgress,
+  estimatedCompletion,
+  timing,
 }: ProgressReportProps) {
+  const now = useNow();
   let statusValue = 100;
   if (status.kind === "in-progress") {
-    statusValue = status.completion * 100;
-  } else if (status.kind === "queued") {
+    // the step count is misleading when one step takes most of the time
+    statusValue = (estimatedProgress ?? status.completion) * 100;
+  } else if (status.kind === "queued" || status.kind === "waiting") {
     statusValue = 0;
   }
 
@@ -25,6 +87,14 @@ export function ProgressReport({
       <span>
         {numSteps}/{numCompleteSteps}
       </span>
+      {status.kind === "in-progress" && estimatedCompletion !== null && (
+        <span className="whitespace-nowrap">
+          {timeLeft(estimatedCompletion, now)}
+        </span>
+      )}
+      {elapsed(timing, now) && (
+        <span className="whitespace-nowrap">{elapsed(timing, now)}</span>
+      )}
     </div>
   );
 }
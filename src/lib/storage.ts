@@ -1,18 +1,19 @@
-import { Pr } from "@/types";
+import { DEFAULT_HOST, Pr } from "@/types";
+import { isSameTarget } from "@/lib/watcher";
+import { invoke } from "@tauri-apps/api/core";
 import { createContext } from "react";
 
 const STORAGE_KEY = "store";
 
 type State = {
   prs: Pr[];
-  token: string | null;
 };
 
 export class Storage {
   state: State;
 
   constructor(state: State | undefined) {
-    this.state = state || { prs: [], token: null };
+    this.state = state || { prs: [] };
   }
 
   public addPr(pr: Pr): void {
@@ -20,17 +21,8 @@ export class Storage {
     this.save();
   }
 
-  public removePr(prNumber: number): void {
-    this.state.prs = this.state.prs.filter((pr) => pr.number !== prNumber);
-    this.save();
-  }
-
-  public getToken(): string | null {
-    return this.state.token;
-  }
-
-  public setToken(token: string) {
-    this.state.token = token;
+  public removePr(removed: Pr): void {
+    this.state.prs = this.state.prs.filter((pr) => !isSameTarget(pr, removed));
     this.save();
   }
 
@@ -43,8 +35,21 @@ export class Storage {
     let state;
     if (rawState) {
       state = JSON.parse(rawState);
+      // tokens used to be stored here, move them to the backend's token store
+      if (state.token) {
+        invoke("set_token", { token: state.token }).catch((e) =>
+          console.error("error migrating token", e)
+        );
+        delete state.token;
+        localStorage.setItem(STORAGE_KEY, JSON.stringify(state));
+      }
+      // PRs stored before hosts were configurable all live on github.com
+      state.prs = state.prs.map((pr: Pr) => ({
+        ...pr,
+        host: pr.host ?? DEFAULT_HOST,
+      }));
     } else {
-      state = { prs: [], token: null };
+      state = { prs: [] };
     }
     return new Storage(state);
   }
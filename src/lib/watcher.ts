@@ -0,0 +1,157 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { useEffect, useState } from "react";
+import {
+  ArchivedPr,
+  Pr,
+  StatusEvent,
+  targetKey,
+  WatchedStatus,
+  watchedStatusFromEvent,
+  WatchTarget,
+} from "@/types";
+
+const PR_STATUS_CHANGED = "pr-status-changed";
+const DISCOVERED_PRS_CHANGED = "discovered-prs-changed";
+
+export async function watchPr(pr: Pr): Promise<void> {
+  await invoke("watch_pr", {
+    host: pr.host,
+    owner: pr.owner,
+    repo: pr.repo,
+    prNumber: pr.number,
+    workflowId: pr.workflowId,
+  });
+}
+
+export async function unwatchPr(pr: Pr): Promise<void> {
+  await invoke("unwatch_pr", {
+    host: pr.host,
+    owner: pr.owner,
+    repo: pr.repo,
+    prNumber: pr.number,
+    workflowId: pr.workflowId,
+  });
+}
+
+// Latest status of every watched PR, keyed by `targetKey`
+export function useWatchedStatuses(): Record<string, WatchedStatus> {
+  const [statuses, setStatuses] = useState<Record<string, WatchedStatus>>({});
+
+  useEffect(() => {
+    const update = (event: StatusEvent) => {
+      const { host, owner, repo, pr_number, workflow_id } = event.target;
+      const key = targetKey(host, owner, repo, pr_number, workflow_id);
+      setStatuses((statuses) => ({
+        ...statuses,
+        [key]: watchedStatusFromEvent(event),
+      }));
+    };
+
+    const unlisten = listen<StatusEvent>(PR_STATUS_CHANGED, (event) =>
+      update(event.payload)
+    );
+    invoke<StatusEvent[]>("watched_pr_statuses").then((events) =>
+      events.forEach(update)
+    );
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  return statuses;
+}
+
+export const prFromTarget = (target: WatchTarget): Pr => ({
+  status: { kind: "unknown" },
+  host: target.host,
+  owner: target.owner,
+  repo: target.repo,
+  number: target.pr_number,
+  workflowId: target.workflow_id,
+});
+
+export const isSameTarget = (a: Pr, b: Pr) =>
+  a.host === b.host &&
+  a.owner === b.owner &&
+  a.repo === b.repo &&
+  a.number === b.number &&
+  a.workflowId === b.workflowId;
+
+const isTarget = (pr: Pr, target: WatchTarget) =>
+  isSameTarget(pr, prFromTarget(target));
+
+// Open PRs of the logged in user that the backend found and is watching
+export function useDiscoveredPrs(): Pr[] {
+  const [prs, setPrs] = useState<Pr[]>([]);
+
+  useEffect(() => {
+    const update = (targets: WatchTarget[]) =>
+      setPrs(targets.map(prFromTarget));
+
+    const unlisten = listen<WatchTarget[]>(DISCOVERED_PRS_CHANGED, (event) =>
+      update(event.payload)
+    );
+    // discovered PRs are archived like any other once they are merged or closed
+    const unlistenStatus = listen<StatusEvent>(PR_STATUS_CHANGED, (event) => {
+      const { target, archived } = event.payload;
+      if (archived) {
+        setPrs((prs) => prs.filter((pr) => !isTarget(pr, target)));
+      }
+    });
+    invoke<WatchTarget[]>("discovered_prs").then(update);
+
+    return () => {
+      unlisten.then((f) => f());
+      unlistenStatus.then((f) => f());
+    };
+  }, []);
+
+  return prs;
+}
+
+type ArchivedPrs = {
+  archived: ArchivedPr[];
+  // forget a single archived PR
+  remove: (target: WatchTarget) => Promise<void>;
+  clear: () => Promise<void>;
+};
+
+// Merged and closed PRs that are no longer polled, most recently archived first
+export function useArchivedPrs(): ArchivedPrs {
+  const [archived, setArchived] = useState<ArchivedPr[]>([]);
+
+  useEffect(() => {
+    const unlisten = listen<StatusEvent>(PR_STATUS_CHANGED, (event) => {
+      const { target, pr, archived } = event.payload;
+      if (!archived || !pr) {
+        return;
+      }
+      setArchived((prs) => [
+        { target, pr, archived_at: Math.floor(Date.now() / 1000) },
+        ...prs.filter((a) => !isTarget(prFromTarget(a.target), target)),
+      ]);
+    });
+    invoke<ArchivedPr[]>("archived_prs").then(setArchived);
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  const remove = async (target: WatchTarget) => {
+    // unwatching also removes the PR from the archive
+    await unwatchPr(prFromTarget(target));
+    setArchived((prs) =>
+      prs.filter((a) => !isTarget(prFromTarget(a.target), target))
+    );
+  };
+
+  const clear = async () => {
+    await invoke("clear_archived_prs");
+    setArchived([]);
+  };
+
+  return { archived, remove, clear };
+}
@@ -1,37 +1,164 @@
-import { useContext, useState } from "react";
-import { Pr } from "../types";
+import { useContext, useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import {
+  Pr,
+  targetKey,
+  watchedStatusFromEvent,
+  WatchTarget,
+} from "../types";
 import { PrStatus } from "../components/PrStatus";
 import { StorageContext } from "@/lib/storage";
 import { Navigate } from "react-router-dom";
 import { Header } from "@/components/Header";
+import {
+  isSameTarget,
+  prFromTarget,
+  unwatchPr,
+  useArchivedPrs,
+  useDiscoveredPrs,
+  useWatchedStatuses,
+  watchPr,
+} from "@/lib/watcher";
+import { useQuery } from "@tanstack/react-query";
+import {
+  Collapsible,
+  CollapsibleContent,
+  CollapsibleTrigger,
+} from "@/components/ui/collapsible";
+import { Button } from "@/components/ui/button";
+import { ChevronsUpDown } from "lucide-react";
 
 export default function Index() {
   const storage = useContext(StorageContext);
 
-  if (!storage.state.token) {
+  const [prs, setPrs] = useState<Pr[]>(storage.state.prs);
+  const statuses = useWatchedStatuses();
+  const discovered = useDiscoveredPrs();
+  const { archived, remove: removeArchived, clear: clearArchived } =
+    useArchivedPrs();
+  const { data: hasToken } = useQuery<boolean>({
+    queryKey: ["has-token"],
+    queryFn: () => invoke("has_token"),
+  });
+
+  useEffect(() => {
+    if (!hasToken) {
+      return;
+    }
+    storage.state.prs.forEach(watchPr);
+    // the backend keeps its own list, which survives the webview's storage being cleared
+    invoke<WatchTarget[]>("watched_prs").then((targets) => {
+      const missing = targets
+        .map(prFromTarget)
+        .filter((pr) => !storage.state.prs.some((p) => isSameTarget(p, pr)));
+      missing.forEach((pr) => storage.addPr(pr));
+      setPrs((prs) => [...prs, ...missing]);
+    });
+  }, [hasToken]);
+
+  // the backend stops polling archived PRs, so stop watching them on the next start too
+  useEffect(() => {
+    archived.forEach(({ target }) => {
+      const stored = prs.find(
+        (pr) =>
+          pr.host === target.host &&
+          pr.owner === target.owner &&
+          pr.repo === target.repo &&
+          pr.number === target.pr_number
+      );
+      if (stored) {
+        setPrs((prs) => prs.filter((pr) => pr !== stored));
+        storage.removePr(stored);
+      }
+    });
+  }, [archived]);
+
+  if (hasToken === false) {
     return <Navigate replace to="/auth" />;
   }
 
-  const [prs, setPrs] = useState<Pr[]>(storage.state.prs);
-
   const addPr = (pr: Pr) => {
     setPrs((prs) => [...prs, pr]);
     storage.addPr(pr);
+    watchPr(pr);
   };
 
-  const removePr = (prNumber: number) => {
-    setPrs((prs) => prs.filter((pr) => pr.number !== prNumber));
-    storage.removePr(prNumber);
+  const removePr = (removed: Pr) => {
+    unwatchPr(removed);
+    setPrs((prs) => prs.filter((pr) => !isSameTarget(pr, removed)));
+    storage.removePr(removed);
   };
 
+  const isSamePr = (a: Pr, b: Pr) =>
+    a.host === b.host &&
+    a.owner === b.owner &&
+    a.repo === b.repo &&
+    a.number === b.number;
+  // discovered PRs are not stored, the backend finds them again on every start
+  const allPrs = [
+    ...prs,
+    ...discovered.filter((d) => !prs.some((pr) => isSamePr(pr, d))),
+  ];
+
   return (
     <div className="flex flex-col p-4 gap-8">
       <Header addPr={addPr} />
       <div className="flex flex-col gap-2">
-        {prs.map((pr) => {
-          return <PrStatus key={pr.number} pr={pr} removePr={removePr} />;
+        {allPrs.map((pr) => {
+          const key = targetKey(
+            pr.host,
+            pr.owner,
+            pr.repo,
+            pr.number,
+            pr.workflowId
+          );
+          return (
+            <PrStatus
+              key={key}
+              pr={pr}
+              status={statuses[key]}
+              removePr={removePr}
+            />
+          );
         })}
       </div>
+      {archived.length > 0 && (
+        <Collapsible className="flex flex-col gap-2">
+          <div className="flex justify-between items-center">
+            <CollapsibleTrigger className="flex items-center gap-2">
+              <p>Archived ({archived.length})</p>
+              <ChevronsUpDown />
+            </CollapsibleTrigger>
+            <Button variant="ghost" onClick={clearArchived}>
+              Clear
+            </Button>
+          </div>
+          <CollapsibleContent className="flex flex-col gap-2">
+            {archived.map(({ target, pr }) => {
+              const key = targetKey(
+                target.host,
+                target.owner,
+                target.repo,
+                target.pr_number,
+                target.workflow_id
+              );
+              return (
+                <PrStatus
+                  key={key}
+                  pr={prFromTarget(target)}
+                  status={watchedStatusFromEvent({
+                    target,
+                    pr,
+                    error: null,
+                    archived: true,
+                  })}
+                  removePr={() => removeArchived(target)}
+                />
+              );
+            })}
+          </CollapsibleContent>
+        </Collapsible>
+      )}
     </div>
   );
 }
@@ -1,6 +1,6 @@
-import { StorageContext } from "@/lib/storage";
 import { zodResolver } from "@hookform/resolvers/zod";
-import { useContext } from "react";
+import { useQuery, useQueryClient } from "@tanstack/react-query";
+import { useState } from "react";
 import { useForm } from "react-hook-form";
 import { z } from "zod";
 import {
@@ -14,9 +14,26 @@ import {
 import { Input } from "../components/ui/input";
 import { Button } from "../components/ui/button";
 import { useNavigate } from "react-router-dom";
+import { invoke } from "@tauri-apps/api/core";
+import { open } from "@tauri-apps/plugin-shell";
+import {
+  ApiError,
+  DeviceLogin,
+  DeviceLoginStatus,
+  errorMessage,
+  GhAccount,
+  TokenReport,
+} from "@/types";
+
+const sleep = (secs: number) =>
+  new Promise((resolve) => setTimeout(resolve, secs * 1000));
 
 const formSchema = z.object({
   token: z.string().min(1),
+  // optional repository to check the token's permissions against
+  repo: z
+    .string()
+    .regex(/^([^/\s]+\/[^/\s]+)?$/, { message: "Expected owner/repo" }),
 });
 
 export function RequestToken() {
@@ -24,20 +41,121 @@ export function RequestToken() {
     resolver: zodResolver(formSchema),
     defaultValues: {
       token: "",
+      repo: "",
     },
   });
 
   const naviagte = useNavigate();
 
-  const storage = useContext(StorageContext);
+  const queryClient = useQueryClient();
+
+  const ghAccounts = useQuery({
+    queryKey: ["gh-cli-accounts"],
+    queryFn: () => invoke<GhAccount[]>("gh_cli_accounts"),
+  });
+
+  const [deviceLogin, setDeviceLogin] = useState<DeviceLogin | null>(null);
+
+  async function loginWithDevice() {
+    let login: DeviceLogin;
+    try {
+      login = await invoke<DeviceLogin>("start_device_login");
+    } catch (e) {
+      form.setError("token", { message: errorMessage(e as ApiError) });
+      return;
+    }
+    setDeviceLogin(login);
+    await open(login.verification_uri);
+
+    let interval = login.interval;
+    try {
+      for (;;) {
+        await sleep(interval);
+        const status = await invoke<DeviceLoginStatus>("poll_device_login");
+        if (status.status === "complete") {
+          break;
+        }
+        interval = status.interval ?? interval;
+      }
+    } catch (e) {
+      form.setError("token", { message: errorMessage(e as ApiError) });
+      return;
+    } finally {
+      setDeviceLogin(null);
+    }
+    await queryClient.invalidateQueries({ queryKey: ["has-token"] });
+    naviagte("/");
+  }
+
+  async function loginWithGhCli(account: GhAccount) {
+    try {
+      await invoke("use_gh_cli_token", { host: account.host });
+      // checks the token now stored for the host
+      await invoke<TokenReport>("validate_token", { host: account.host });
+    } catch (e) {
+      await invoke("clear_token", { host: account.host });
+      form.setError("token", { message: errorMessage(e as ApiError) });
+      return;
+    }
+    await queryClient.invalidateQueries({ queryKey: ["has-token"] });
+    naviagte("/");
+  }
+
+  async function onSubmit(values: z.infer<typeof formSchema>) {
+    const [owner, repo] = values.repo ? values.repo.split("/") : [null, null];
+    let report: TokenReport;
+    try {
+      report = await invoke<TokenReport>("validate_token", {
+        token: values.token,
+        owner,
+        repo,
+      });
+    } catch (e) {
+      form.setError("token", { message: errorMessage(e as ApiError) });
+      return;
+    }
+
+    const missing = report.permissions.filter((p) => !p.granted);
+    if (missing.length > 0) {
+      const permissions = missing.map((p) => p.permission).join(", ");
+      form.setError("repo", {
+        message: `The token cannot access ${values.repo}, it needs ${permissions}`,
+      });
+      return;
+    }
 
-  function onSubmit(values: z.infer<typeof formSchema>) {
-    storage.setToken(values.token);
+    try {
+      await invoke("set_token", { token: values.token });
+    } catch (e) {
+      form.setError("token", { message: errorMessage(e as ApiError) });
+      return;
+    }
+    await queryClient.invalidateQueries({ queryKey: ["has-token"] });
     naviagte("/");
   }
 
   return (
     <Form {...form}>
+      {deviceLogin ? (
+        <p>
+          Enter the code <code>{deviceLogin.user_code}</code> at{" "}
+          {deviceLogin.verification_uri}
+        </p>
+      ) : (
+        <Button variant="outline" onClick={loginWithDevice}>
+          Log in with GitHub
+        </Button>
+      )}
+      {ghAccounts.data?.map((account) => (
+        <Button
+          key={account.host}
+          variant="outline"
+          onClick={() => loginWithGhCli(account)}
+        >
+          Use gh CLI login ({account.user ? `${account.user}@` : ""}
+          {account.host})
+        </Button>
+      ))}
       <form onSubmit={form.handleSubmit(onSubmit)}>
         <FormField
           control={form.control}
@@ -52,7 +170,22 @@ export function RequestToken() {
             </FormItem>
           )}
         />
-        <Button type="submit">Submit</Button>
+        <FormField
+          control={form.control}
+          name="repo"
+          render={({ field }) => (
+            <FormItem>
+              <FormLabel>Check access to (optional)</FormLabel>
+              <FormControl>
+                <Input placeholder="owner/repo" {...field} />
+              </FormControl>
+              <FormMessage />
+            </FormItem>
+          )}
+        />
+        <Button type="submit" disabled={form.formState.isSubmitting}>
+          Submit
+        </Button>
       </form>
     </Form>
   );
@@ -1,22 +1,49 @@
+// name of the public GitHub host, see `hosts` in the app config
+export const DEFAULT_HOST = "github.com";
+
+export type HostConfig = {
+  name: string;
+  api_base_url: string;
+  web_base_url: string;
+  ca_bundle: string | null;
+  // client ID of the OAuth app used to log in with the device flow
+  oauth_client_id: string | null;
+};
+
 export type Pr = {
   status: Status;
+  // name of the configured host the repository lives on
+  host: string;
   number: number;
   repo: string;
-  workflowId: number;
+  // null watches every workflow that ran against the PR
+  workflowId: number | null;
   owner: string;
 };
 
 export type RawStatus =
   | "Queued"
+  | "Waiting"
   | { InProgress: number }
   | "Succeeded"
   | "Failed"
+  | "Cancelled"
+  | "Skipped"
+  | "TimedOut"
+  | "ActionRequired"
+  | "Neutral"
+  | "Stale"
+  | "StartupFailure"
   | "Unknown";
 
 export type QueuedStatus = {
   kind: "queued";
 };
 
+export type WaitingStatus = {
+  kind: "waiting";
+};
+
 export type InProgressStatus = {
   kind: "in-progress";
   completion: number;
@@ -30,25 +57,77 @@ export type FailedStatus = {
   kind: "failed";
 };
 
+export type CancelledStatus = {
+  kind: "cancelled";
+};
+
+export type SkippedStatus = {
+  kind: "skipped";
+};
+
+export type TimedOutStatus = {
+  kind: "timed-out";
+};
+
+export type ActionRequiredStatus = {
+  kind: "action-required";
+};
+
+export type NeutralStatus = {
+  kind: "neutral";
+};
+
+export type StaleStatus = {
+  kind: "stale";
+};
+
+export type StartupFailureStatus = {
+  kind: "startup-failure";
+};
+
 export type UnknownStatus = {
   kind: "unknown";
 };
 
 export type Status =
   | QueuedStatus
+  | WaitingStatus
   | InProgressStatus
   | SucceededStatus
   | FailedStatus
+  | CancelledStatus
+  | SkippedStatus
+  | TimedOutStatus
+  | ActionRequiredStatus
+  | NeutralStatus
+  | StaleStatus
+  | StartupFailureStatus
   | UnknownStatus;
 
 export const statusFromRaw = (raw: RawStatus): Status => {
   switch (raw) {
     case "Queued":
       return { kind: "queued" };
+    case "Waiting":
+      return { kind: "waiting" };
     case "Succeeded":
       return { kind: "succeeded" };
     case "Failed":
       return { kind: "failed" };
+    case "Cancelled":
+      return { kind: "cancelled" };
+    case "Skipped":
+      return { kind: "skipped" };
+    case "TimedOut":
+      return { kind: "timed-out" };
+    case "ActionRequired":
+      return { kind: "action-required" };
+    case "Neutral":
+      return { kind: "neutral" };
+    case "Stale":
+      return { kind: "stale" };
+    case "StartupFailure":
+      return { kind: "startup-failure" };
     case "Unknown":
       return { kind: "unknown" };
     default:
@@ -56,15 +135,313 @@ export const statusFromRaw = (raw: RawStatus): Status => {
   }
 };
 
+// unix timestamps (in seconds) of when a run was queued, started and completed, and the
+// seconds it spent waiting for a runner
+export type Timing = {
+  createdAt: number | null;
+  startedAt: number | null;
+  completedAt: number | null;
+  queuedSecs: number | null;
+};
+
+export type TimingResponse = {
+  created_at: string | null;
+  started_at: string | null;
+  completed_at: string | null;
+  queued_secs: number | null;
+};
+
+const unixTime = (at: string | null | undefined): number | null =>
+  at ? Math.floor(Date.parse(at) / 1000) : null;
+
+const timingFromResponse = (timing: TimingResponse | undefined): Timing => ({
+  createdAt: unixTime(timing?.created_at),
+  startedAt: unixTime(timing?.started_at),
+  completedAt: unixTime(timing?.completed_at),
+  queuedSecs: timing?.queued_secs ?? null,
+});
+
+export type WorkflowStatus = {
+  workflowId: number;
+  name: string;
+  status: Status;
+  numSteps: number;
+  numCompleteSteps: number;
+  runUrl: string;
+  timing: Timing;
+};
+
+// whether the PR itself is still open, as opposed to the status of its CI
+export type PrState = "open" | "merged" | "closed";
+
 export type StatusPayload = {
   owner: string;
   repo: string;
   status: Status;
+  state: PrState;
   title: string;
+  branch: string;
   number: number;
   description: string;
   numSteps: number;
   numCompleteSteps: number;
   prUrl: string;
   runUrl: string;
+  workflows: WorkflowStatus[];
+  // weighted by how long each job took in past runs, null without any history
+  estimatedProgress: number | null;
+  // unix timestamp (seconds)
+  estimatedCompletion: number | null;
+  timing: Timing;
+};
+
+export type WorkflowStatusResponse = {
+  workflow_id: number;
+  run_id: number;
+  name: string;
+  status: RawStatus;
+  num_steps: number;
+  num_complete_steps: number;
+  run_url: string;
+  timing?: TimingResponse;
+};
+
+export type PrStatusResponse = {
+  status: RawStatus;
+  state: PrState;
+  // unix timestamp (seconds) the PR was merged or closed at
+  closed_at: number | null;
+  title: string;
+  branch: string;
+  description: string;
+  num_steps: number;
+  num_complete_steps: number;
+  pr_url: string;
+  run_url: string;
+  workflows: WorkflowStatusResponse[];
+  estimated_progress: number | null;
+  estimated_completion: number | null;
+  // missing from PRs archived before it was added
+  timing?: TimingResponse;
+};
+
+export type WatchTarget = {
+  host: string;
+  owner: string;
+  repo: string;
+  pr_number: number;
+  workflow_id: number | null;
+};
+
+// result of the `parse_github_reference` command
+export type ParsedReference = {
+  target: WatchTarget;
+  title: string;
+  // workflows that ran against the head of the PR
+  workflows: { id: number; name: string; filename: string }[];
+};
+
+// errors returned by commands and status events, tagged by `kind`
+export type ApiError =
+  // `credential` names the stored credential the token came from
+  | { kind: "unauthorized"; credential: string | null }
+  | { kind: "forbidden"; required: string | null; credential: string | null }
+  | { kind: "not_found"; resource: string; credential: string | null }
+  // unix timestamp in seconds
+  | { kind: "rate_limited"; until: number }
+  | { kind: "network"; message: string }
+  | { kind: "status"; status: number }
+  | { kind: "decode"; message: string }
+  | { kind: "no_runs"; head_sha: string; previous_runs: boolean }
+  | { kind: "unknown_host"; host: string }
+  | { kind: "invalid_reference"; input: string }
+  | { kind: "no_token"; host: string; owner: string | null }
+  | { kind: "storage"; message: string }
+  | { kind: "device_login_unavailable" }
+  | { kind: "device_login_expired" }
+  | { kind: "access_denied" }
+  | { kind: "oauth"; error: string; description: string | null };
+
+export const errorTitle = (error: ApiError): string => {
+  switch (error.kind) {
+    case "unauthorized":
+      return "Token expired";
+    case "forbidden":
+      return "Missing permission";
+    case "not_found":
+      return "Not found";
+    case "rate_limited":
+      return "Rate limited";
+    case "network":
+      return "Network error";
+    case "no_runs":
+      return error.previous_runs ? "Waiting for CI" : "No workflow runs";
+    default:
+      return "Error";
+  }
+};
+
+const tokenName = (credential: string | null): string =>
+  credential ? `token '${credential}'` : "token";
+
+export const errorMessage = (error: ApiError): string => {
+  switch (error.kind) {
+    case "unauthorized":
+      return `GitHub rejected the ${tokenName(error.credential)}, it may have expired or been revoked. Log out and add a new token.`;
+    case "forbidden":
+      return error.required
+        ? `The ${tokenName(error.credential)} is missing a permission, it needs ${error.required}.`
+        : `The ${tokenName(error.credential)} is missing a permission.`;
+    case "not_found":
+      return `${error.resource} was not found, or the ${tokenName(error.credential)} cannot access it.`;
+    case "rate_limited":
+      return `Rate limited by GitHub until ${new Date(
+        error.until * 1000
+      ).toLocaleTimeString()}.`;
+    case "network":
+      return `Could not reach GitHub: ${error.message}`;
+    case "status":
+      return `Unexpected ${error.status} response from GitHub.`;
+    case "decode":
+      return `Unexpected response from GitHub: ${error.message}`;
+    case "no_runs":
+      return error.previous_runs
+        ? `No workflow has run yet for the latest commit ${error.head_sha.slice(0, 7)}.`
+        : `No workflow runs found for commit ${error.head_sha.slice(0, 7)}.`;
+    case "unknown_host":
+      return `The host ${error.host} is not configured.`;
+    case "invalid_reference":
+      return `${error.input} is not a PR, workflow run or branch URL, or owner/repo#number.`;
+    case "no_token":
+      return error.owner
+        ? `No token stored for ${error.owner} on ${error.host}, add one first.`
+        : `No token stored for ${error.host}, log in first.`;
+    case "storage":
+      return `Could not store the token: ${error.message}`;
+    case "device_login_unavailable":
+      return "Logging in with GitHub is not configured for this host, set its oauth_client_id in the config file.";
+    case "device_login_expired":
+      return "The login code has expired, start again.";
+    case "access_denied":
+      return "The login was cancelled.";
+    case "oauth":
+      return `Could not log in: ${error.description ?? error.error}`;
+  }
+};
+
+export type PermissionCheck = {
+  permission: string;
+  granted: boolean;
+  error: ApiError | null;
+};
+
+// result of the `validate_token` command
+export type TokenReport = {
+  login: string;
+  // null for fine-grained tokens
+  scopes: string[] | null;
+  // unix timestamp in seconds, null if the token never expires
+  expires_at: number | null;
+  expires_soon: boolean;
+  permissions: PermissionCheck[];
+};
+
+// a stored credential from the `list_credentials` command, without its token
+export type CredentialInfo = {
+  name: string;
+  host: string;
+  // empty if the credential is used for every owner on the host
+  owners: string[];
+  gh_cli: boolean;
+};
+
+// result of the `start_device_login` command
+export type DeviceLogin = {
+  user_code: string;
+  verification_uri: string;
+  // seconds
+  expires_in: number;
+  interval: number;
+};
+
+// result of the `poll_device_login` command
+export type DeviceLoginStatus =
+  | { status: "pending"; interval: number | null }
+  | { status: "complete" };
+
+// a host the gh CLI is logged in to, from the `gh_cli_accounts` command
+export type GhAccount = {
+  host: string;
+  user: string | null;
+  source:
+    | { kind: "env"; var: string }
+    | { kind: "hosts_file" }
+    | { kind: "keyring" };
+};
+
+// payload of the `pr-status-changed` event
+export type StatusEvent = {
+  target: WatchTarget;
+  pr: PrStatusResponse | null;
+  error: ApiError | null;
+  // the PR was merged or closed a while ago and is no longer polled
+  archived: boolean;
+};
+
+// result of the `archived_prs` command, with the final status of the PR
+export type ArchivedPr = {
+  target: WatchTarget;
+  pr: PrStatusResponse;
+  // unix timestamp (seconds)
+  archived_at: number;
+};
+
+export type WatchedStatus = {
+  data: StatusPayload | null;
+  error: ApiError | null;
+};
+
+export const targetKey = (
+  host: string,
+  owner: string,
+  repo: string,
+  number: number,
+  workflowId: number | null
+): string => `${host}/${owner}/${repo}#${number}:${workflowId ?? "all"}`;
+
+export const watchedStatusFromEvent = (event: StatusEvent): WatchedStatus => {
+  const { target, pr, error } = event;
+  if (!pr) {
+    return { data: null, error };
+  }
+
+  return {
+    data: {
+      owner: target.owner,
+      repo: target.repo,
+      status: statusFromRaw(pr.status),
+      state: pr.state,
+      title: pr.title,
+      branch: pr.branch,
+      number: target.pr_number,
+      description: pr.description,
+      numSteps: pr.num_steps,
+      numCompleteSteps: pr.num_complete_steps,
+      prUrl: pr.pr_url,
+      runUrl: pr.run_url,
+      workflows: pr.workflows.map((workflow) => ({
+        workflowId: workflow.workflow_id,
+        name: workflow.name,
+        status: statusFromRaw(workflow.status),
+        numSteps: workflow.num_steps,
+        numCompleteSteps: workflow.num_complete_steps,
+        runUrl: workflow.run_url,
+        timing: timingFromResponse(workflow.timing),
+      })),
+      estimatedProgress: pr.estimated_progress,
+      estimatedCompletion: pr.estimated_completion,
+      timing: timingFromResponse(pr.timing),
+    },
+    error,
+  };
 };